@@ -3,16 +3,17 @@ mod gui; //
 use proyecto1::threadcity::city::City;
 use proyecto1::threadcity::entities::VehicleType;
 use proyecto1::mypthreads::{
-    my_thread_create,
-    with_threads,
+    my_thread_spawn,
+    my_thread_run,
+    my_thread_preempt_point,
+    my_thread_sleep,
     SchedulerType,
     my_thread_id,
-    set_current_thread_id,
+    MutexProtocol,
 };
 use proyecto1::scheduler;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use std::thread::{self, sleep};
+use std::thread;
 use crate::gui::{run_gui, SharedCity};
 
 fn run_simulation(city: SharedCity) {
@@ -33,90 +34,72 @@ fn run_simulation(city: SharedCity) {
         c.spawn_vehicle((0,2), (4,2), VehicleType::Boat);
     }
 
-    
-
+    // ⏱️ Round Robin expropiativo: cada hilo corre a lo sumo 500 ms seguidos
+    scheduler::set_preemption(true);
+    scheduler::set_quantum_ms(SchedulerType::RoundRobin, 500);
 
     // 🚗 Crear un hilo simulado del tipo "Auto" usando mypthreads
-let city_clone = Arc::clone(&city);
-let _car_thread = my_thread_create(
-    move || {
-        for step_count in 0..20 {
-
-            let tid = my_thread_id();
-            {
-                let mut c = city_clone.lock().unwrap();
-
-                // Pequeña lógica de puente
+    let city_clone = Arc::clone(&city);
+    let car_thread = my_thread_spawn(
+        move || {
+            let mut steps = 0;
+            for step_count in 0..20 {
+                steps = step_count + 1;
+                let tid = my_thread_id();
+                // Pequeña lógica de puente. Los cruces duermen y esperan a
+                // otros hilos: se hacen con la ciudad suelta, para no trabar a
+                // la GUI ni a quien reciba el CPU por expropiación
                 if step_count == 5 {
-                    c.cross_bridge(VehicleType::Car, 1, tid);
+                    let bridge = city_clone.lock().unwrap().bridge(1);
+                    City::cross(&bridge, VehicleType::Car, tid);
                 }
 
-                let done = c.step(tid);
-                c.print_state();
+                let (done, crossings) = {
+                    let mut c = city_clone.lock().unwrap();
+                    let (done, crossings) = c.advance();
+                    let bridges: Vec<_> =
+                        crossings.into_iter().map(|(vtype, id)| (vtype, c.bridge(id))).collect();
+                    (done, bridges)
+                };
+                for (vtype, bridge) in &crossings {
+                    City::cross(bridge, *vtype, tid);
+                }
+                city_clone.lock().unwrap().print_state();
 
                 if done {
                     println!("🚗 El Auto llegó a su destino ✅");
                     break;
-    // Crear un hilo simulado del tipo "Auto" usando mypthreads
-    let city_clone: Arc<Mutex<City>> = Arc::clone(&city);
-    let _car_thread = my_thread_create(
-        move || {
-            for step_count in 0..20 {
-                {
-                    let mut c = city_clone.lock().unwrap();
-
-                    // Pequeña lógica de puente
-                    if step_count == 5 {
-                        c.cross_bridge(VehicleType::Car, 1);
-                    }
-
-                    let done = c.step();
-                    c.print_state();
-
-                    if done {
-                        println!("🚗 El Auto llegó a su destino ✅");
-                        break;
-                    }
                 }
-                std::thread::sleep(Duration::from_millis(300));
-            }
-            std::thread::sleep(Duration::from_millis(300));
-        }
-    },
-    SchedulerType::RoundRobin, // ← tipo de planificación
-).unwrap();
-
+                // duerme solo este hilo; los demás siguen corriendo
+                my_thread_sleep(300);
 
-    // 🧠 Bucle principal del planificador
-    loop {
-        let mut all_done = false;
-        if let Some(tid) = scheduler::scheduler_next() {
-            // Set the current thread ID before executing
-            set_current_thread_id(tid);
-            with_threads(|table| {
-                let t = &table[tid];
-                if let Some(f) = &t.start_routine {
-                    f(); // ejecuta el hilo elegido (el Auto)
-                }
-            });
-            // Verifica si ya todos los vehículos llegaron
-            let mut c = city.lock().unwrap();
-            all_done = c.step(tid); // si step() devuelve true, todos llegaron
-        }
-        if all_done {
-            println!("🏁 Todos los vehículos llegaron, fin de la simulación.");
-            break;
-        }
-
-        sleep(Duration::from_millis(100));
-    }
+                // Si ya se le acabó el quantum, cede el CPU y sigue aquí en su próximo turno
+                my_thread_preempt_point();
+            }
+            steps
+        },
+        SchedulerType::RoundRobin, // ← tipo de planificación
+    ).unwrap();
+
+    // 📋 Hilo coordinador: espera al auto y reporta las estadísticas del viaje
+    let _coordinator = my_thread_spawn(
+        move || match car_thread.join() {
+            Ok(steps) => println!("📋 El auto terminó su recorrido en {steps} pasos"),
+            Err(e) => println!("📋 No se pudo obtener el resultado del auto: {e}"),
+        },
+        SchedulerType::RoundRobin,
+    ).unwrap();
+
+    // 🧠 El planificador corre los hilos hasta que terminen
+    my_thread_run();
 
     println!("Simulation finished.");
 }
 
 fn main() {
-    // Crear ciudad compartida
-    let city: SharedCity = Arc::new(Mutex::new(City::new(5, 5)));
+    // Crear ciudad compartida; los puentes usan herencia de prioridad para que
+    // una ambulancia RT no quede esperando detrás de un auto RR
+    let city: SharedCity = Arc::new(Mutex::new(City::with_protocol(5, 5, MutexProtocol::Inherit)));
 
     // Lanzar la simulación en un hilo del SO
     let city_for_sim = city.clone();
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::sync::Mutex as StdMutex;
+
+use crate::mypthreads::thread::{block_current, cancel_pending, wake_thread};
+use crate::mypthreads::{my_thread_testcancel, MyMutex, MyThreadError, MyThreadId};
+use crate::scheduler::lock_state;
+
+/// Variable de condición de nuestra biblioteca (similar a `pthread_cond_t`).
+///
+/// Siempre se usa junto con un `MyMutex` que protege el predicado:
+/// - `wait` suelta el mutex, deja al hilo BLOCKED y vuelve a tomar el mutex
+///   al despertar.
+/// - `signal` despierta al hilo que lleva más tiempo esperando.
+/// - `broadcast` despierta a todos.
+///
+/// Como en pthreads, el predicado se debe revisar en un ciclo:
+///
+/// ```rust,no_run
+/// use proyecto1::mypthreads::{my_mutex_init, my_mutex_lock, my_mutex_unlock, MyCond, my_thread_id};
+///
+/// let m = my_mutex_init();
+/// let cond = MyCond::new();
+/// let ready = std::sync::atomic::AtomicBool::new(false);
+///
+/// // dentro de un hilo de la biblioteca:
+/// let tid = my_thread_id();
+/// my_mutex_lock(&m, tid).unwrap();
+/// while !ready.load(std::sync::atomic::Ordering::SeqCst) {
+///     cond.wait(&m, tid).unwrap();
+/// }
+/// my_mutex_unlock(&m, tid).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MyCond {
+    /// Hilos bloqueados en `wait`, en orden de llegada.
+    waiters: StdMutex<VecDeque<MyThreadId>>,
+}
+
+impl MyCond {
+    /// Crea una variable de condición sin hilos esperando.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suelta `mutex`, bloquea al hilo actual hasta un `signal`/`broadcast`
+    /// y vuelve a tomar `mutex` antes de retornar.
+    ///
+    /// Devuelve error si `current_tid` no es el dueño de `mutex` o si volver a
+    /// tomarlo cerraría un deadlock.
+    ///
+    /// Es un punto de cancelación. Como en pthreads, el hilo cancelado vuelve a
+    /// tomar `mutex` antes de desenrollarse, así que debe registrar un
+    /// manejador (`my_thread_cleanup_push`) que lo suelte.
+    pub fn wait(&self, mutex: &MyMutex, current_tid: MyThreadId) -> Result<(), MyThreadError> {
+        lock_state(&self.waiters).push_back(current_tid);
+
+        if let Err(e) = mutex.release(current_tid) {
+            self.remove_waiter(current_tid);
+            return Err(e);
+        }
+
+        // entre soltar el mutex y bloquearse no hay puntos de expropiación,
+        // así que ningún `signal` se puede perder
+        if !block_current(true) {
+            // fuera de un hilo de la biblioteca no hay a quién ceder el CPU
+            self.remove_waiter(current_tid);
+        }
+
+        // si nos despertó una cancelación pero un `signal` ya nos había
+        // escogido, se lo pasamos a otro
+        if cancel_pending() && !self.remove_waiter(current_tid) {
+            self.signal();
+        }
+
+        mutex.acquire(current_tid, false)?;
+        my_thread_testcancel();
+        Ok(())
+    }
+
+    /// Despierta a un hilo que esté esperando (el más antiguo), si hay alguno.
+    pub fn signal(&self) {
+        let next = lock_state(&self.waiters).pop_front();
+        if let Some(tid) = next {
+            wake_thread(tid);
+        }
+    }
+
+    /// Despierta a todos los hilos que estén esperando.
+    pub fn broadcast(&self) {
+        let all: Vec<MyThreadId> = lock_state(&self.waiters).drain(..).collect();
+        for tid in all {
+            wake_thread(tid);
+        }
+    }
+
+    /// "Destruye" la condición: olvida a los hilos que estuvieran esperando.
+    pub fn destroy(&self) {
+        lock_state(&self.waiters).clear();
+    }
+
+    /// Saca a `tid` de la cola; devuelve `true` si estaba.
+    fn remove_waiter(&self, tid: MyThreadId) -> bool {
+        let mut waiters = lock_state(&self.waiters);
+        let before = waiters.len();
+        waiters.retain(|&w| w != tid);
+        waiters.len() != before
+    }
+}
+
+/// Función de conveniencia que crea una nueva variable de condición.
+pub fn my_cond_init() -> MyCond {
+    MyCond::new()
+}
+
+/// Función de conveniencia que limpia la variable de condición.
+pub fn my_cond_destroy(cond: &MyCond) {
+    cond.destroy();
+}
+
+/// Envoltorio para `MyCond::wait`.
+pub fn my_cond_wait(
+    cond: &MyCond,
+    mutex: &MyMutex,
+    current_tid: MyThreadId,
+) -> Result<(), MyThreadError> {
+    cond.wait(mutex, current_tid)
+}
+
+/// Envoltorio para `MyCond::signal`.
+pub fn my_cond_signal(cond: &MyCond) {
+    cond.signal();
+}
+
+/// Envoltorio para `MyCond::broadcast`.
+pub fn my_cond_broadcast(cond: &MyCond) {
+    cond.broadcast();
+}
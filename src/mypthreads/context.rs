@@ -0,0 +1,240 @@
+//! Cambio de contexto en espacio de usuario.
+//!
+//! Cada hilo de la biblioteca tiene su propia pila (`Stack`) y un `Context`
+//! donde se guarda el puntero de pila cuando el hilo no está corriendo.
+//! Los registros *callee-saved* (y en x86_64 también MXCSR y la palabra de
+//! control del x87) se guardan en la propia pila del hilo antes de cambiar,
+//! así que el `Context` solo necesita recordar el `sp`.
+
+use std::io;
+use std::mem::size_of;
+use std::ptr;
+
+/// Tamaño de pila por defecto para cada hilo (256 KiB).
+pub const DEFAULT_STACK_SIZE: usize = 256 * 1024;
+
+/// Contexto guardado de un hilo: el puntero de pila donde quedaron sus registros.
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Context {
+    sp: usize,
+}
+
+/// Pila privada de un hilo, reservada con `mmap`.
+///
+/// Debajo de la pila queda una página de guarda sin permisos (`PROT_NONE`):
+/// un hilo que se pasa de su pila falla con `SIGSEGV` en vez de pisar en
+/// silencio la memoria de al lado.
+pub struct Stack {
+    /// Inicio del mapeo, donde está la página de guarda.
+    base: *mut u8,
+    /// Tamaño total del mapeo, guarda incluida.
+    len: usize,
+    /// Tamaño de la página de guarda.
+    guard: usize,
+}
+
+// SAFETY: el mapeo es de la `Stack` y nadie más lo libera; moverla a otro
+// hilo del SO no cambia nada.
+unsafe impl Send for Stack {}
+unsafe impl Sync for Stack {}
+
+impl Stack {
+    /// Reserva una pila nueva de `size` bytes (redondeados a páginas), más la
+    /// página de guarda.
+    ///
+    /// # Panics
+    /// Si el sistema no puede reservar la memoria.
+    pub fn new(size: usize) -> Self {
+        let page = page_size();
+        let guard = page;
+        let len = size.div_ceil(page).max(1) * page + guard;
+
+        // SAFETY: mapeo anónimo nuevo; solo se toca la memoria que devuelve.
+        unsafe {
+            let base = libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if base == libc::MAP_FAILED {
+                panic!("no se pudo reservar la pila del hilo: {}", io::Error::last_os_error());
+            }
+            // la pila crece hacia abajo: la guarda va en la dirección más baja
+            if libc::mprotect(base, guard, libc::PROT_NONE) != 0 {
+                let err = io::Error::last_os_error();
+                libc::munmap(base, len);
+                panic!("no se pudo proteger la guarda de la pila: {err}");
+            }
+            Self { base: base.cast(), len, guard }
+        }
+    }
+
+    /// Dirección del tope de la pila, alineada a 16 bytes.
+    fn top(&self) -> usize {
+        let end = self.base as usize + self.len;
+        end & !0xf
+    }
+
+    /// Dirección más baja que el hilo puede usar (justo encima de la guarda).
+    fn bottom(&self) -> usize {
+        self.base as usize + self.guard
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        // SAFETY: `base` y `len` son exactamente los del `mmap` de `new`.
+        unsafe {
+            libc::munmap(self.base.cast(), self.len);
+        }
+    }
+}
+
+/// Tamaño de página del sistema.
+fn page_size() -> usize {
+    // SAFETY: `sysconf` no tiene precondiciones.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(page).ok().filter(|&p| p > 0).unwrap_or(4096)
+}
+
+impl Context {
+    /// Prepara un contexto que, al activarse por primera vez, salta a `entry`
+    /// usando `stack` como pila.
+    ///
+    /// `entry` nunca debe retornar: no hay a dónde volver.
+    pub fn new(stack: &Stack, entry: extern "C" fn() -> !) -> Self {
+        let top = stack.top();
+        let sp = arch::init_stack(top, entry as usize);
+        debug_assert!(sp >= stack.bottom() + size_of::<usize>());
+        Self { sp }
+    }
+}
+
+/// Guarda el contexto actual en `from` y reanuda el hilo guardado en `to`.
+///
+/// Vuelve cuando alguien cambie de nuevo hacia `from`.
+///
+/// # Safety
+/// `from` y `to` deben apuntar a contextos válidos, y `to` debe haber sido
+/// creado con [`Context::new`] o guardado por una llamada previa a `switch`.
+/// Ningún lock que necesite el hilo destino puede estar tomado al cambiar.
+pub unsafe fn switch(from: *mut Context, to: *const Context) {
+    arch::switch_context(from, to);
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use super::Context;
+
+    /// MXCSR al arrancar un proceso: todas las excepciones enmascaradas,
+    /// redondeo al más cercano.
+    const DEFAULT_MXCSR: u32 = 0x1f80;
+    /// Palabra de control del x87 al arrancar: precisión extendida, redondeo
+    /// al más cercano, excepciones enmascaradas.
+    const DEFAULT_FPU_CW: u16 = 0x037f;
+
+    /// Deja la pila lista para que el primer `ret` de `switch_context`
+    /// salte a `entry` con la alineación que pide la ABI System V.
+    pub(super) fn init_stack(top: usize, entry: usize) -> usize {
+        // Al entrar a `entry` se espera rsp ≡ 8 (mod 16).
+        let ret_slot = top - 16;
+        // rbp, rbx, r12, r13, r14, r15 en cero debajo de la dirección de retorno,
+        // y debajo de ellos MXCSR y la palabra de control del x87.
+        let regs = ret_slot - 6 * 8;
+        let sp = regs - 8;
+        unsafe {
+            *(ret_slot as *mut usize) = entry;
+            for i in 0..6 {
+                *((regs + i * 8) as *mut usize) = 0;
+            }
+            *(sp as *mut u32) = DEFAULT_MXCSR;
+            *((sp + 4) as *mut u16) = DEFAULT_FPU_CW;
+        }
+        sp
+    }
+
+    #[unsafe(naked)]
+    pub(super) unsafe extern "C" fn switch_context(_from: *mut Context, _to: *const Context) {
+        core::arch::naked_asm!(
+            "push rbp",
+            "push rbx",
+            "push r12",
+            "push r13",
+            "push r14",
+            "push r15",
+            // la ABI también pide conservar MXCSR y la palabra de control del x87
+            "sub rsp, 8",
+            "stmxcsr [rsp]",
+            "fnstcw [rsp + 4]",
+            "mov [rdi], rsp",
+            "mov rsp, [rsi]",
+            "ldmxcsr [rsp]",
+            "fldcw [rsp + 4]",
+            "add rsp, 8",
+            "pop r15",
+            "pop r14",
+            "pop r13",
+            "pop r12",
+            "pop rbx",
+            "pop rbp",
+            "ret",
+        )
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use super::Context;
+
+    /// Deja la pila lista para que `switch_context` cargue `entry` en x30
+    /// y el `ret` salte ahí.
+    pub(super) fn init_stack(top: usize, entry: usize) -> usize {
+        // x19..x28, x29, x30 y d8..d15 ocupan 160 bytes.
+        let sp = top - 160;
+        unsafe {
+            for i in 0..20 {
+                *((sp + i * 8) as *mut usize) = 0;
+            }
+            // x30 (lr) va en el offset 88.
+            *((sp + 88) as *mut usize) = entry;
+        }
+        sp
+    }
+
+    #[unsafe(naked)]
+    pub(super) unsafe extern "C" fn switch_context(_from: *mut Context, _to: *const Context) {
+        core::arch::naked_asm!(
+            "sub sp, sp, #160",
+            "stp x19, x20, [sp, #0]",
+            "stp x21, x22, [sp, #16]",
+            "stp x23, x24, [sp, #32]",
+            "stp x25, x26, [sp, #48]",
+            "stp x27, x28, [sp, #64]",
+            "stp x29, x30, [sp, #80]",
+            "stp d8, d9, [sp, #96]",
+            "stp d10, d11, [sp, #112]",
+            "stp d12, d13, [sp, #128]",
+            "stp d14, d15, [sp, #144]",
+            "mov x2, sp",
+            "str x2, [x0]",
+            "ldr x2, [x1]",
+            "mov sp, x2",
+            "ldp x19, x20, [sp, #0]",
+            "ldp x21, x22, [sp, #16]",
+            "ldp x23, x24, [sp, #32]",
+            "ldp x25, x26, [sp, #48]",
+            "ldp x27, x28, [sp, #64]",
+            "ldp x29, x30, [sp, #80]",
+            "ldp d8, d9, [sp, #96]",
+            "ldp d10, d11, [sp, #112]",
+            "ldp d12, d13, [sp, #128]",
+            "ldp d14, d15, [sp, #144]",
+            "add sp, sp, #160",
+            "ret",
+        )
+    }
+}
@@ -0,0 +1,211 @@
+//! Detección de deadlocks con un grafo de espera (*wait-for graph*).
+//!
+//! Cada hilo bloqueado espera exactamente una cosa, así que tiene a lo sumo
+//! una arista saliente:
+//! - si espera un `MyMutex`, apunta al dueño actual del mutex;
+//! - si hizo `join`, apunta al hilo que espera (`waiting_thread_id`).
+//!
+//! Un ciclo en ese grafo es un deadlock: nadie del ciclo puede avanzar.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex as StdMutex;
+
+use crate::mypthreads::runtime;
+use crate::mypthreads::{with_threads, MyThreadId, ThreadTable};
+
+/// Qué está esperando un hilo bloqueado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockedOn {
+    /// Espera el mutex con este ID.
+    Mutex(usize),
+    /// Hizo `join` sobre este hilo.
+    Join(MyThreadId),
+    /// Duerme hasta este instante de `scheduler::now_ms` (`my_thread_sleep`).
+    Sleep(u64),
+}
+
+/// Arista del grafo de espera: `waiter` no puede avanzar hasta que el otro hilo lo haga.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitEdge {
+    /// `waiter` espera el mutex `mutex_id`, que tiene `owner`.
+    Mutex {
+        waiter: MyThreadId,
+        mutex_id: usize,
+        owner: MyThreadId,
+    },
+    /// `waiter` hizo `join` sobre `target`.
+    Join {
+        waiter: MyThreadId,
+        target: MyThreadId,
+    },
+}
+
+impl WaitEdge {
+    /// Hilo que espera.
+    pub fn waiter(&self) -> MyThreadId {
+        match *self {
+            WaitEdge::Mutex { waiter, .. } | WaitEdge::Join { waiter, .. } => waiter,
+        }
+    }
+
+    /// Hilo esperado.
+    pub fn holder(&self) -> MyThreadId {
+        match *self {
+            WaitEdge::Mutex { owner, .. } => owner,
+            WaitEdge::Join { target, .. } => target,
+        }
+    }
+}
+
+/// Un ciclo de espera: cada arista lleva a la siguiente y la última vuelve a la primera.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadlockCycle {
+    pub edges: Vec<WaitEdge>,
+}
+
+impl DeadlockCycle {
+    /// Hilos atrapados en el ciclo, en orden.
+    pub fn threads(&self) -> Vec<MyThreadId> {
+        self.edges.iter().map(WaitEdge::waiter).collect()
+    }
+
+    /// IDs de los mutex involucrados en el ciclo.
+    pub fn mutexes(&self) -> Vec<usize> {
+        self.edges
+            .iter()
+            .filter_map(|e| match *e {
+                WaitEdge::Mutex { mutex_id, .. } => Some(mutex_id),
+                WaitEdge::Join { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// Estado de detección de un runtime.
+#[derive(Debug, Default)]
+pub(crate) struct DeadlockState {
+    /// Si es `true`, `lock` y `join` fallan en vez de cerrar un ciclo de espera.
+    detection: AtomicBool,
+    /// Dueño actual de cada mutex tomado (ID del mutex → hilo).
+    mutex_owners: StdMutex<HashMap<usize, MyThreadId>>,
+    /// Qué espera cada hilo bloqueado en un mutex o en un `join`; se anota al
+    /// bloquearse y se borra al despertar (`set_waiting`).
+    waits: StdMutex<HashMap<MyThreadId, BlockedOn>>,
+}
+
+/// Activa o desactiva la detección al bloquearse (en el runtime actual).
+///
+/// Con la detección activa, un `lock` o `join` que cerraría un ciclo de espera
+/// devuelve `MyThreadError::Deadlock` (como `EDEADLK`) en lugar de bloquear
+/// al hilo para siempre.
+pub fn set_deadlock_detection(enabled: bool) {
+    runtime::current().deadlock.detection.store(enabled, Ordering::SeqCst);
+}
+
+/// Indica si la detección al bloquearse está activa.
+pub fn deadlock_detection_enabled() -> bool {
+    runtime::current().deadlock.detection.load(Ordering::SeqCst)
+}
+
+/// Anota (o borra, con `None`) el dueño del mutex `mutex_id`.
+pub(crate) fn set_mutex_owner(mutex_id: usize, owner: Option<MyThreadId>) {
+    let rt = runtime::current();
+    let mut owners = rt.deadlock.mutex_owners.lock().unwrap();
+    match owner {
+        Some(tid) => owners.insert(mutex_id, tid),
+        None => owners.remove(&mutex_id),
+    };
+}
+
+/// Anota (o borra, con `None`) lo que espera `tid`. Solo los mutex y los
+/// `join` son aristas del grafo; el resto se ignora.
+pub(crate) fn set_waiting(tid: MyThreadId, blocked_on: Option<BlockedOn>) {
+    let rt = runtime::current();
+    let mut waits = rt.deadlock.waits.lock().unwrap();
+    match blocked_on {
+        Some(on @ (BlockedOn::Mutex(_) | BlockedOn::Join(_))) => waits.insert(tid, on),
+        _ => waits.remove(&tid),
+    };
+}
+
+/// Arista saliente de cada hilo que está esperando algo.
+///
+/// Sale de lo anotado con `set_waiting`, sin recorrer la tabla: cuesta lo
+/// mismo con 10 hilos que con 1000 si los bloqueados son los mismos.
+pub(crate) fn wait_edges(table: &ThreadTable) -> HashMap<MyThreadId, WaitEdge> {
+    let rt = runtime::current();
+    let waits = rt.deadlock.waits.lock().unwrap();
+    let owners = rt.deadlock.mutex_owners.lock().unwrap();
+
+    waits
+        .iter()
+        .filter_map(|(&waiter, &on)| match on {
+            BlockedOn::Mutex(mutex_id) => {
+                let &owner = owners.get(&mutex_id)?;
+                Some((waiter, WaitEdge::Mutex { waiter, mutex_id, owner }))
+            }
+            // el `join` deja de ser arista cuando el hilo esperado termina
+            BlockedOn::Join(target) => table
+                .get(target)
+                .filter(|t| t.waiting_thread_id == Some(waiter))
+                .map(|_| (waiter, WaitEdge::Join { waiter, target })),
+            BlockedOn::Sleep(_) => None,
+        })
+        .collect()
+}
+
+/// Sigue las aristas desde `start`; si vuelve a `start`, devuelve el ciclo.
+fn cycle_from(start: MyThreadId, edges: &HashMap<MyThreadId, WaitEdge>) -> Option<DeadlockCycle> {
+    let mut path = Vec::new();
+    let mut seen = HashSet::new();
+    let mut tid = start;
+
+    while let Some(&edge) = edges.get(&tid) {
+        if !seen.insert(tid) {
+            return None; // ciclo que no pasa por `start`
+        }
+        path.push(edge);
+        tid = edge.holder();
+        if tid == start {
+            return Some(DeadlockCycle { edges: path });
+        }
+    }
+    None
+}
+
+/// Busca todos los ciclos de espera entre los hilos del runtime actual.
+///
+/// Cada ciclo se reporta una sola vez, empezando por su hilo de menor ID.
+pub fn detect_deadlocks() -> Vec<DeadlockCycle> {
+    with_threads(|table| {
+        let edges = wait_edges(table);
+        let mut starts: Vec<MyThreadId> = edges.keys().copied().collect();
+        starts.sort_unstable();
+
+        let mut reported = HashSet::new();
+        let mut cycles = Vec::new();
+        for tid in starts {
+            if reported.contains(&tid) {
+                continue;
+            }
+            if let Some(cycle) = cycle_from(tid, &edges) {
+                reported.extend(cycle.threads());
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    })
+}
+
+/// ¿Cerraría un ciclo que `waiter` pase a esperar en `edge`?
+///
+/// Solo se evalúa con la detección activa; si no, siempre es `false`.
+pub(crate) fn would_deadlock(table: &ThreadTable, edge: WaitEdge) -> bool {
+    if !deadlock_detection_enabled() {
+        return false;
+    }
+    let mut edges = wait_edges(table);
+    edges.insert(edge.waiter(), edge);
+    cycle_from(edge.waiter(), &edges).is_some()
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::mypthreads::MyThreadId;
+
+/// Errores que devuelve la API de `mypthreads`.
+///
+/// Cada variante lleva los IDs involucrados, para que quien llama pueda
+/// distinguir los casos con `match` en lugar de comparar mensajes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MyThreadError {
+    /// No existe un hilo con ese ID.
+    NoSuchThread(MyThreadId),
+    /// La tabla de hilos ya tiene `max` hilos.
+    MaxThreadsReached { max: usize },
+    /// La operación necesita un hilo actual y no hay ninguno corriendo.
+    NoCurrentThread,
+    /// Se intentó hacer `join` sobre un hilo *detached*.
+    JoinDetached(MyThreadId),
+    /// Un hilo intentó hacer `join` sobre sí mismo.
+    JoinSelf(MyThreadId),
+    /// `target` ya tiene otro hilo (`waiter`) esperándolo con `join`, o ya
+    /// fue recogido con `join` (`waiter` es `None` si se recogió desde fuera
+    /// de los hilos de la biblioteca).
+    AlreadyJoined {
+        target: MyThreadId,
+        waiter: Option<MyThreadId>,
+    },
+    /// `tid` intentó soltar el mutex `mutex_id`, que no le pertenece.
+    NotOwner {
+        mutex_id: usize,
+        tid: MyThreadId,
+        owner: Option<MyThreadId>,
+    },
+    /// Bloquear a `tid` cerraría un ciclo de espera (como `EDEADLK`).
+    Deadlock(MyThreadId),
+    /// Un hilo de Lottery necesita al menos un ticket.
+    InvalidTickets(MyThreadId),
+    /// El `nice` de Cfs va de -20 a 19.
+    InvalidNice(MyThreadId),
+    /// El hilo terminó sin un valor de salida del tipo esperado.
+    MissingExitValue(MyThreadId),
+    /// El hilo terminó porque fue cancelado con `my_thread_cancel`.
+    Canceled(MyThreadId),
+    /// Parámetros RT inválidos: período, deadline o WCET en 0, o WCET mayor
+    /// que el deadline.
+    InvalidRtParams(MyThreadId),
+    /// Con este hilo, el conjunto de tareas RT no pasa el test de admisión.
+    NotSchedulable(MyThreadId),
+    /// La operación necesita un hilo periódico (`my_thread_set_rt_params`).
+    NotPeriodic(MyThreadId),
+}
+
+impl fmt::Display for MyThreadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MyThreadError::NoSuchThread(tid) => write!(f, "thread {tid} does not exist"),
+            MyThreadError::MaxThreadsReached { max } => {
+                write!(f, "max threads reached ({max})")
+            }
+            MyThreadError::NoCurrentThread => write!(f, "no current thread"),
+            MyThreadError::JoinDetached(tid) => write!(f, "thread {tid} is detached"),
+            MyThreadError::JoinSelf(tid) => write!(f, "thread {tid} cannot join itself"),
+            MyThreadError::AlreadyJoined { target, waiter } => match waiter {
+                Some(waiter) => {
+                    write!(f, "thread {target} is already joined by thread {waiter}")
+                }
+                None => write!(f, "thread {target} is already joined"),
+            },
+            MyThreadError::NotOwner { mutex_id, tid, owner } => match owner {
+                Some(owner) => write!(
+                    f,
+                    "thread {tid} is not the owner of mutex {mutex_id} (owner: {owner})"
+                ),
+                None => write!(f, "thread {tid} is not the owner of mutex {mutex_id} (unlocked)"),
+            },
+            MyThreadError::Deadlock(tid) => write!(f, "deadlock detected (thread {tid})"),
+            MyThreadError::InvalidTickets(tid) => {
+                write!(f, "tickets must be >= 1 (thread {tid})")
+            }
+            MyThreadError::InvalidNice(tid) => {
+                write!(f, "nice must be between -20 and 19 (thread {tid})")
+            }
+            MyThreadError::MissingExitValue(tid) => {
+                write!(f, "thread {tid} finished without an exit value of the expected type")
+            }
+            MyThreadError::Canceled(tid) => write!(f, "thread {tid} was canceled"),
+            MyThreadError::InvalidRtParams(tid) => write!(
+                f,
+                "invalid real-time parameters for thread {tid} (need 0 < wcet <= deadline, period > 0)"
+            ),
+            MyThreadError::NotSchedulable(tid) => {
+                write!(f, "real-time task set is not schedulable with thread {tid}")
+            }
+            MyThreadError::NotPeriodic(tid) => {
+                write!(f, "thread {tid} has no real-time period")
+            }
+        }
+    }
+}
+
+impl Error for MyThreadError {}
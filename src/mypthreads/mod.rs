@@ -1,26 +1,65 @@
 pub mod mutex;
 pub mod thread;
+pub mod cond;
+pub mod semaphore;
+pub mod deadlock;
+pub mod error;
+mod context;
+pub(crate) mod runtime;
 
 
+pub use error::MyThreadError;
+pub use runtime::Runtime;
 pub use mutex::*;
+pub use cond::*;
+pub use semaphore::*;
+pub use deadlock::{
+    BlockedOn,
+    WaitEdge,
+    DeadlockCycle,
+    detect_deadlocks,
+    set_deadlock_detection,
+    deadlock_detection_enabled,
+};
 pub use thread::{
     MyThreadId,
     ThreadState,
     SchedulerType,
     ThreadControlBlock,
+    RtParams,
+    DeadlineMissAction,
+    ThreadTable,
     with_threads,
     with_threads_mut,
     my_thread_create,
+    my_thread_spawn,
+    MyJoinHandle,
     my_thread_join,
+    my_thread_join_value,
+    my_thread_exit,
+    my_thread_cancel,
+    my_thread_testcancel,
+    my_thread_cleanup_push,
+    my_thread_cleanup_pop,
     my_thread_detach,
     my_thread_chsched,
     my_thread_yield_,
+    my_thread_preempt_point,
+    my_thread_sleep,
+    my_thread_sleep_until,
     my_thread_end,
     my_thread_set_tickets,
+    my_thread_set_nice,
+    my_thread_set_currency,
     my_thread_set_deadline_ms,
+    my_thread_set_rt_params,
+    my_thread_set_miss_action,
+    my_thread_wait_next_period,
     my_thread_id,
-    set_current_thread_id
-    
+    my_thread_set_max_threads,
+    my_thread_max_threads,
+    my_thread_run,
+
 };
 
 /* 
@@ -1,62 +1,305 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex as StdMutex;
 
-use crate::mypthreads::{my_thread_yield_, MyThreadId};
+use crate::mypthreads::deadlock::{self, BlockedOn, WaitEdge};
+use crate::mypthreads::thread::{
+    block_current, cancel_pending, effective_params, set_blocked_on, set_boost, wake_thread,
+    Urgency,
+};
+use crate::mypthreads::{
+    my_thread_preempt_point, my_thread_testcancel, with_threads, MyThreadError, MyThreadId,
+    SchedulerType,
+    ThreadControlBlock, ThreadTable,
+};
+use crate::scheduler::{self, lock_state};
 
-/// Estructura que representa un mutex cooperativo de nuestra biblioteca.
+/// Orden en que `unlock` escoge al siguiente dueño entre los hilos en espera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MutexPolicy {
+    /// El que llegó primero.
+    #[default]
+    Fifo,
+    /// El de clase con mayor precedencia (ver `scheduler::class_precedence`);
+    /// dentro de la clase, el de deadline más cercano o más tickets.
+    Priority,
+    /// El de `deadline_ms` más cercano; los hilos sin deadline van al final.
+    Deadline,
+}
+
+/// Protocolo contra la inversión de prioridad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MutexProtocol {
+    /// El dueño conserva su clase y deadline.
+    #[default]
+    None,
+    /// Herencia de prioridad: mientras haya un hilo más urgente esperando,
+    /// el dueño se planifica con la clase, el deadline y la prioridad fija
+    /// (RM/DM) de ese hilo.
+    Inherit,
+    /// Techo de prioridad: quien toma el mutex se planifica al menos con esta
+    /// clase, deadline (EDF) y prioridad fija (RM/DM) hasta soltarlo.
+    Ceiling {
+        scheduler_type: SchedulerType,
+        deadline_ms: Option<u64>,
+        fixed_priority: Option<u64>,
+    },
+}
+
+/// Contador para darle a cada mutex un ID único.
+static NEXT_MUTEX_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Estado interno del mutex, protegido por un solo lock.
+#[derive(Debug, Default)]
+struct MutexState {
+    /// ID del hilo que posee el mutex actualmente.
+    owner: Option<MyThreadId>,
+    /// Hilos BLOCKED esperando el mutex, en orden de llegada.
+    waiters: VecDeque<MyThreadId>,
+}
+
+/// Estructura que representa un mutex de nuestra biblioteca.
 ///
 /// La idea es similar a `pthread_mutex_t`, pero implementado en espacio de usuario:
-/// - `locked` indica si el recurso está tomado o no.
 /// - `owner` guarda el ID del hilo que tiene el mutex (si alguno).
+/// - los hilos que lo piden ocupado quedan BLOCKED en una cola de espera, y
+///   `unlock` le pasa el mutex directamente al siguiente según `policy`.
+/// - `protocol` opcionalmente evita la inversión de prioridad.
 #[derive(Debug)]
 pub struct MyMutex {
-    /// Indica si el mutex está bloqueado (`true`) o libre (`false`).
-    locked: AtomicBool,
-    /// ID del hilo que posee el mutex actualmente.
-    owner: StdMutex<Option<MyThreadId>>,
+    id: usize,
+    state: StdMutex<MutexState>,
+    policy: MutexPolicy,
+    protocol: MutexProtocol,
+}
+
+impl Default for MyMutex {
+    fn default() -> Self {
+        Self::with_options(MutexPolicy::default(), MutexProtocol::default())
+    }
 }
 
 impl MyMutex {
-    /// Crea un nuevo mutex desbloqueado, sin dueño.
+    /// Crea un nuevo mutex desbloqueado, sin dueño, con cola FIFO.
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crea un mutex desbloqueado que despierta a sus esperas según `policy`.
+    pub fn with_policy(policy: MutexPolicy) -> Self {
+        Self::with_options(policy, MutexProtocol::default())
+    }
+
+    /// Crea un mutex desbloqueado, con cola FIFO, que usa `protocol` contra
+    /// la inversión de prioridad.
+    pub fn with_protocol(protocol: MutexProtocol) -> Self {
+        Self::with_options(MutexPolicy::default(), protocol)
+    }
+
+    /// Crea un mutex desbloqueado con la política de espera y el protocolo dados.
+    pub fn with_options(policy: MutexPolicy, protocol: MutexProtocol) -> Self {
         Self {
-            locked: AtomicBool::new(false),
-            owner: StdMutex::new(None),
+            id: NEXT_MUTEX_ID.fetch_add(1, Ordering::Relaxed),
+            state: StdMutex::new(MutexState::default()),
+            policy,
+            protocol,
         }
     }
 
-    /// Intenta adquirir el mutex bloqueando hasta lograrlo.
+    /// Identificador único del mutex.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Política con la que se escoge al siguiente dueño.
+    pub fn policy(&self) -> MutexPolicy {
+        self.policy
+    }
+
+    /// Protocolo contra la inversión de prioridad.
+    pub fn protocol(&self) -> MutexProtocol {
+        self.protocol
+    }
+
+    /// Hilo que tiene el mutex ahora mismo, si alguno.
+    pub fn owner(&self) -> Option<MyThreadId> {
+        lock_state(&self.state).owner
+    }
+
+    /// Adquiere el mutex, bloqueando hasta lograrlo.
+    ///
+    /// Si está ocupado, el hilo `current_tid` entra a la cola de espera y queda
+    /// BLOCKED (sin consumir CPU) hasta que `unlock` le entregue el mutex.
     ///
-    /// Usa espera **cooperativa**, es decir, mientras el mutex esté ocupado
-    /// llama a `my_thread_yield_()` para ceder la CPU a otros hilos.
+    /// Con la detección de deadlocks activa (`set_deadlock_detection`), devuelve
+    /// `MyThreadError::Deadlock` sin bloquear si esperar cerraría un ciclo.
     ///
-    /// `current_tid` es el ID del hilo que intenta tomar el mutex.
-    pub fn lock(&self, current_tid: MyThreadId) {
-        while self.locked.swap(true, Ordering::Acquire) {
-            // Otro hilo tiene el mutex → cedo el procesador cooperativamente.
-            my_thread_yield_();
+    /// La espera es un punto de cancelación: un hilo cancelado mientras espera
+    /// sale de la cola sin tomar el mutex.
+    pub fn lock(&self, current_tid: MyThreadId) -> Result<(), MyThreadError> {
+        self.acquire(current_tid, true)
+    }
+
+    /// `lock`, pero con `cancelable == false` la espera no es un punto de
+    /// cancelación (la usa `MyCond::wait` para volver a tomar el mutex).
+    pub(crate) fn acquire(&self, current_tid: MyThreadId, cancelable: bool) -> Result<(), MyThreadError> {
+        let owner = {
+            let mut st = lock_state(&self.state);
+            let Some(owner) = st.owner else {
+                self.set_owner(&mut st, Some(current_tid));
+                drop(st);
+                self.on_acquire(current_tid);
+                return Ok(());
+            };
+
+            let edge = WaitEdge::Mutex { waiter: current_tid, mutex_id: self.id, owner };
+            if with_threads(|table| deadlock::would_deadlock(table, edge)) {
+                return Err(MyThreadError::Deadlock(current_tid));
+            }
+
+            st.waiters.push_back(current_tid);
+            set_blocked_on(current_tid, Some(BlockedOn::Mutex(self.id)));
+            owner
+        };
+
+        // herencia: el dueño corre con nuestra urgencia mientras esperamos
+        if self.protocol == MutexProtocol::Inherit {
+            set_boost(owner, self.id, effective_params(current_tid));
         }
 
-        // En este punto el mutex estaba libre y ya lo marcamos como locked.
-        *self.owner.lock().unwrap() = Some(current_tid);
+        loop {
+            if !block_current(cancelable) {
+                // fuera de un hilo de la biblioteca no se puede bloquear: esperamos activamente
+                lock_state(&self.state).waiters.retain(|&w| w != current_tid);
+                while !self.try_lock(current_tid) {
+                    std::thread::yield_now();
+                }
+                break;
+            }
+
+            // `unlock` nos pasó el mutex antes de despertarnos
+            if lock_state(&self.state).owner == Some(current_tid) {
+                break;
+            }
+
+            if cancelable && cancel_pending() {
+                self.withdraw(current_tid);
+                my_thread_testcancel();
+            }
+        }
+
+        set_blocked_on(current_tid, None);
+        Ok(())
+    }
+
+    /// Saca a `tid` de la cola de espera (se canceló mientras esperaba) y
+    /// deja al dueño heredando solo de los que siguen esperando.
+    fn withdraw(&self, tid: MyThreadId) {
+        let (owner, remaining) = {
+            let mut st = lock_state(&self.state);
+            st.waiters.retain(|&w| w != tid);
+            (st.owner, st.waiters.iter().copied().collect::<Vec<_>>())
+        };
+        set_blocked_on(tid, None);
+
+        if let (MutexProtocol::Inherit, Some(owner)) = (self.protocol, owner) {
+            set_boost(owner, self.id, None);
+            for waiter in remaining {
+                set_boost(owner, self.id, effective_params(waiter));
+            }
+        }
+    }
+
+    /// Cambia el dueño y lo anota en el grafo de espera.
+    fn set_owner(&self, st: &mut MutexState, owner: Option<MyThreadId>) {
+        st.owner = owner;
+        deadlock::set_mutex_owner(self.id, owner);
     }
 
     /// Libera el mutex si el hilo actual es su dueño.
     ///
-    /// Devuelve `Ok(())` si se liberó correctamente, o un error si
-    /// otro hilo intentó liberar un mutex que no le pertenece.
-    pub fn unlock(&self, current_tid: MyThreadId) -> Result<(), &'static str> {
-        let mut owner_guard = self.owner.lock().unwrap();
-
-        match *owner_guard {
-            Some(owner_id) if owner_id == current_tid => {
-                // El hilo actual sí es el dueño → puede liberar.
-                *owner_guard = None;
-                self.locked.store(false, Ordering::Release);
-                Ok(())
+    /// Devuelve `Ok(())` si se liberó correctamente, o `MyThreadError::NotOwner`
+    /// si otro hilo intentó liberar un mutex que no le pertenece.
+    ///
+    /// Es un punto de expropiación: si el hilo agotó su quantum, cede el CPU
+    /// justo después de liberar.
+    pub fn unlock(&self, current_tid: MyThreadId) -> Result<(), MyThreadError> {
+        self.release(current_tid)?;
+        my_thread_preempt_point();
+        Ok(())
+    }
+
+    /// Libera el mutex sin pasar por un punto de expropiación.
+    ///
+    /// Si hay hilos esperando, el mutex pasa directamente al que escoja la
+    /// política, que queda READY ya siendo el dueño.
+    ///
+    /// La usan las primitivas que se bloquean justo después de soltar el
+    /// mutex (p. ej. `MyCond::wait`) y no pueden ceder el CPU en medio.
+    pub(crate) fn release(&self, current_tid: MyThreadId) -> Result<(), MyThreadError> {
+        let (next, remaining) = {
+            let mut st = lock_state(&self.state);
+            if st.owner != Some(current_tid) {
+                return Err(MyThreadError::NotOwner {
+                    mutex_id: self.id,
+                    tid: current_tid,
+                    owner: st.owner,
+                });
+            }
+            let next = self.pick_waiter(&mut st.waiters);
+            self.set_owner(&mut st, next);
+            (next, st.waiters.iter().copied().collect::<Vec<_>>())
+        };
+
+        // lo prestado por este mutex se devuelve al soltarlo
+        if self.protocol != MutexProtocol::None {
+            set_boost(current_tid, self.id, None);
+        }
+
+        if let Some(tid) = next {
+            self.on_acquire(tid);
+            if self.protocol == MutexProtocol::Inherit {
+                for waiter in remaining {
+                    set_boost(tid, self.id, effective_params(waiter));
+                }
             }
-            _ => Err("current thread is not the owner of this mutex"),
+            wake_thread(tid);
+        }
+        Ok(())
+    }
+
+    /// `tid` acaba de quedar como dueño: aplica el techo de prioridad si hay.
+    fn on_acquire(&self, tid: MyThreadId) {
+        if let MutexProtocol::Ceiling { scheduler_type, deadline_ms, fixed_priority } = self.protocol {
+            set_boost(
+                tid,
+                self.id,
+                Some(Urgency { sched: scheduler_type, deadline_ms, fixed_priority }),
+            );
+        }
+    }
+
+    /// Saca de la cola al próximo dueño según la política del mutex.
+    fn pick_waiter(&self, waiters: &mut VecDeque<MyThreadId>) -> Option<MyThreadId> {
+        if waiters.is_empty() {
+            return None;
         }
+        let idx = match self.policy {
+            MutexPolicy::Fifo => 0,
+            MutexPolicy::Priority => {
+                let precedence = scheduler::class_precedence();
+                let rt_policy = scheduler::rt_policy();
+                with_threads(|table| {
+                    best_index(waiters, table, |t| {
+                        Urgency::of(t).key(t.tickets, &precedence, rt_policy)
+                    })
+                })
+            }
+            MutexPolicy::Deadline => with_threads(|table| {
+                best_index(waiters, table, |t| (t.deadline_ms.unwrap_or(u64::MAX), 0))
+            }),
+        };
+        waiters.remove(idx)
     }
 
     /// Intenta adquirir el mutex **sin bloquear**.
@@ -64,10 +307,11 @@ impl MyMutex {
     /// - Si lo logra, devuelve `true` y se convierte en el dueño.
     /// - Si ya estaba bloqueado por otro hilo, devuelve `false` inmediatamente.
     pub fn try_lock(&self, current_tid: MyThreadId) -> bool {
-        // Si locked era false, lo pone en true y devuelve false → entramos.
-        // Si locked era true, devuelve true → alguien más lo tiene.
-        if !self.locked.swap(true, Ordering::Acquire) {
-            *self.owner.lock().unwrap() = Some(current_tid);
+        let mut st = lock_state(&self.state);
+        if st.owner.is_none() {
+            self.set_owner(&mut st, Some(current_tid));
+            drop(st);
+            self.on_acquire(current_tid);
             true
         } else {
             false
@@ -77,13 +321,29 @@ impl MyMutex {
     /// "Destruye" el mutex.
     ///
     /// En esta implementación no libera recursos del sistema operativo,
-    /// pero dejamos el mutex en estado limpio (libre y sin dueño).
+    /// pero dejamos el mutex en estado limpio (libre, sin dueño y sin cola).
     pub fn destroy(&self) {
-        self.locked.store(false, Ordering::Release);
-        *self.owner.lock().unwrap() = None;
+        let mut st = lock_state(&self.state);
+        self.set_owner(&mut st, None);
+        st.waiters.clear();
     }
 }
 
+/// Posición del hilo en espera con la menor clave; en empate, el más antiguo.
+fn best_index<K: Ord>(
+    waiters: &VecDeque<MyThreadId>,
+    table: &ThreadTable,
+    key: impl Fn(&ThreadControlBlock) -> K,
+) -> usize {
+    waiters
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &tid)| table.get(tid).map(|t| (key(t), i)))
+        .min()
+        .map(|(_, i)| i)
+        .unwrap_or(0)
+}
+
 /// Función de conveniencia que crea un nuevo mutex.
 ///
 /// Esto imita el estilo de `my_mutex_init()` del enunciado, pero en Rust
@@ -109,12 +369,12 @@ pub fn my_mutex_destroy(mutex: &MyMutex) {
 /// Envoltorio para `MyMutex::lock`, con una API más cercana al enunciado.
 ///
 /// El código que llame a esta función debe conocer su propio `MyThreadId`.
-pub fn my_mutex_lock(mutex: &MyMutex, current_tid: MyThreadId) {
-    mutex.lock(current_tid);
+pub fn my_mutex_lock(mutex: &MyMutex, current_tid: MyThreadId) -> Result<(), MyThreadError> {
+    mutex.lock(current_tid)
 }
 
 /// Envoltorio para `MyMutex::unlock`.
-pub fn my_mutex_unlock(mutex: &MyMutex, current_tid: MyThreadId) -> Result<(), &'static str> {
+pub fn my_mutex_unlock(mutex: &MyMutex, current_tid: MyThreadId) -> Result<(), MyThreadError> {
     mutex.unlock(current_tid)
 }
 
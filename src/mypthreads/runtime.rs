@@ -0,0 +1,219 @@
+//! Runtimes independientes de `mypthreads`.
+//!
+//! Un `Runtime` es dueño de todo el estado de la biblioteca: tabla de hilos,
+//! hilo actual, contexto del planificador, estado de cada política, reloj y
+//! detección de deadlocks. Dos runtimes no se ven entre sí, así que se pueden
+//! correr dos simulaciones a la vez (cada una en su hilo del SO) o aislar
+//! pruebas.
+//!
+//! Las funciones libres (`my_thread_create`, `my_mutex_lock`,
+//! `scheduler::set_preemption`, ...) trabajan sobre el **runtime actual**:
+//! el que se activó con `Runtime::enter` (o `Runtime::run`) en este hilo del
+//! SO, o si no hay ninguno, el runtime global de siempre.
+
+use once_cell::sync::Lazy;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use crate::mypthreads::context::Context;
+use crate::mypthreads::deadlock::DeadlockState;
+use crate::mypthreads::thread::{self, MyJoinHandle, ThreadTable};
+use crate::mypthreads::{MyThreadError, MyThreadId, SchedulerType};
+use crate::scheduler::{self, ClockMode, NoPreempt, SchedulerState};
+
+/// Estado compartido de un runtime.
+pub(crate) struct RuntimeInner {
+    /// Tabla donde se guardan **todos** los hilos creados en este runtime.
+    pub(crate) threads: Mutex<ThreadTable>,
+    /// ID del hilo que está actualmente en ejecución.
+    /// Si es `None` significa que no hay hilo corriendo.
+    pub(crate) current: Mutex<Option<MyThreadId>>,
+    /// Contexto del planificador (`my_thread_run`): a él vuelven los hilos
+    /// cuando ceden, se bloquean o terminan.
+    pub(crate) scheduler_context: Mutex<Box<Context>>,
+    pub(crate) scheduler: SchedulerState,
+    pub(crate) deadlock: DeadlockState,
+}
+
+/// Un runtime de `mypthreads` con su propia tabla de hilos, planificador y reloj.
+///
+/// Clonar un `Runtime` da otra referencia al **mismo** runtime.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_thread_yield_, Runtime, SchedulerType};
+///
+/// let a = Runtime::new();
+/// let b = Runtime::new();
+///
+/// a.create(|| my_thread_yield_(), SchedulerType::RoundRobin).unwrap();
+/// b.create(|| my_thread_yield_(), SchedulerType::RoundRobin).unwrap();
+/// assert_eq!(a.with_threads(|t| t.len()), 1);
+///
+/// a.run();
+/// b.run();
+/// ```
+#[derive(Clone)]
+pub struct Runtime {
+    inner: Arc<RuntimeInner>,
+}
+
+/// Runtime que usan las funciones libres cuando no se activó otro.
+static GLOBAL: Lazy<Runtime> = Lazy::new(Runtime::new);
+
+thread_local! {
+    /// Runtime activado con `enter` en este hilo del SO.
+    static CURRENT_RUNTIME: RefCell<Option<Arc<RuntimeInner>>> = const { RefCell::new(None) };
+}
+
+impl Runtime {
+    /// Crea un runtime vacío, con la configuración por defecto.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(RuntimeInner {
+                threads: Mutex::new(ThreadTable::new()),
+                current: Mutex::new(None),
+                scheduler_context: Mutex::new(Box::default()),
+                scheduler: SchedulerState::new(),
+                deadlock: DeadlockState::default(),
+            }),
+        }
+    }
+
+    /// Crea un runtime cuyos sorteos de Lottery salen de `seed`: dos runtimes
+    /// con la misma semilla y los mismos hilos planifican igual.
+    ///
+    /// Usa el reloj simulado (`ClockMode::Virtual` de 1 ms por tick), porque
+    /// con el reloj real la corrida depende del tiempo de pared.
+    pub fn with_seed(seed: u64) -> Self {
+        let rt = Self::new();
+        rt.enter(|| {
+            scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+            scheduler::set_lottery_seed(seed);
+        });
+        rt
+    }
+
+    /// El runtime global, el que usan las funciones libres por defecto.
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// El runtime actual en este hilo del SO.
+    pub fn current() -> Self {
+        Self { inner: current().share() }
+    }
+
+    /// Ejecuta `f` con este runtime como runtime actual: dentro de `f`, las
+    /// funciones libres de la biblioteca trabajan sobre él.
+    ///
+    /// Un hilo de la biblioteca no debe cambiar de runtime mientras corre.
+    pub fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Devuelve el runtime anterior aunque `f` entre en pánico.
+        struct Restore(Option<Arc<RuntimeInner>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT_RUNTIME.with(|c| *c.borrow_mut() = previous);
+            }
+        }
+
+        let previous = CURRENT_RUNTIME.with(|c| c.replace(Some(self.inner.clone())));
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// Como `my_thread_run`, pero despachando los hilos de este runtime.
+    pub fn run(&self) {
+        self.enter(thread::my_thread_run);
+    }
+
+    /// Como `my_thread_create`, pero crea el hilo en este runtime.
+    pub fn create<F>(
+        &self,
+        start_routine: F,
+        scheduler_type: SchedulerType,
+    ) -> Result<MyThreadId, MyThreadError>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.enter(|| thread::my_thread_create(start_routine, scheduler_type))
+    }
+
+    /// Como `my_thread_spawn`, pero crea el hilo en este runtime.
+    ///
+    /// El `join` del manejador se debe hacer dentro de este runtime (desde
+    /// uno de sus hilos o dentro de `enter`).
+    pub fn spawn<F, T>(
+        &self,
+        start_routine: F,
+        scheduler_type: SchedulerType,
+    ) -> Result<MyJoinHandle<T>, MyThreadError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Any + Send + 'static,
+    {
+        self.enter(|| thread::my_thread_spawn(start_routine, scheduler_type))
+    }
+
+    /// Como `with_threads`, pero sobre la tabla de este runtime.
+    pub fn with_threads<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&ThreadTable) -> R,
+    {
+        f(&self.inner.threads.lock().unwrap())
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Referencia al runtime actual. Mientras exista, el hilo que la tiene no se
+/// expropia por señal: puede estar usando los locks del runtime.
+pub(crate) struct Current {
+    inner: Arc<RuntimeInner>,
+    _no_preempt: NoPreempt,
+}
+
+impl Current {
+    /// Otra referencia al mismo runtime, para usarla desde otro hilo del SO.
+    pub(crate) fn share(&self) -> Arc<RuntimeInner> {
+        self.inner.clone()
+    }
+}
+
+impl Deref for Current {
+    type Target = RuntimeInner;
+
+    fn deref(&self) -> &RuntimeInner {
+        &self.inner
+    }
+}
+
+/// Estado del runtime actual en este hilo del SO (el global si no se activó otro).
+pub(crate) fn current() -> Current {
+    let no_preempt = NoPreempt::new();
+    let inner = CURRENT_RUNTIME
+        .with(|c| c.borrow().clone())
+        .unwrap_or_else(|| GLOBAL.inner.clone());
+    Current { inner, _no_preempt: no_preempt }
+}
+
+/// Como `current`, pero sin esperar, entrar en pánico ni reservar memoria:
+/// `None` si se está cambiando de runtime en este momento o si el runtime
+/// global todavía no se creó. La usa el manejador de `SIGALRM`.
+pub(crate) fn try_current() -> Option<Current> {
+    let no_preempt = NoPreempt::new();
+    let inner = CURRENT_RUNTIME.with(|c| c.try_borrow().ok().map(|rt| rt.clone()))?;
+    let inner = match inner {
+        Some(inner) => inner,
+        // crear el global aquí reservaría memoria dentro del manejador
+        None => Lazy::get(&GLOBAL)?.inner.clone(),
+    };
+    Some(Current { inner, _no_preempt: no_preempt })
+}
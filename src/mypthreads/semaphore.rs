@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::sync::Mutex as StdMutex;
+
+use crate::mypthreads::thread::{block_current, cancel_pending, wake_thread};
+use crate::mypthreads::{my_thread_testcancel, MyThreadId};
+use crate::scheduler::lock_state;
+
+/// Estado interno del semáforo, protegido por un solo lock.
+#[derive(Debug, Default)]
+struct SemState {
+    /// Permisos disponibles.
+    count: usize,
+    /// Hilos bloqueados en `wait`, en orden de llegada.
+    waiters: VecDeque<MyThreadId>,
+}
+
+/// Semáforo contador de nuestra biblioteca (similar a `sem_t`).
+///
+/// Sirve para modelar recursos con capacidad N: carriles de un puente,
+/// espacios de un parqueo, muelles, etc.
+/// - `wait` toma un permiso; si no hay, el hilo queda BLOCKED.
+/// - `post` devuelve un permiso; si hay hilos esperando, se lo entrega
+///   directamente al más antiguo.
+#[derive(Debug)]
+pub struct MySemaphore {
+    state: StdMutex<SemState>,
+}
+
+impl MySemaphore {
+    /// Crea un semáforo con `initial` permisos disponibles.
+    pub fn new(initial: usize) -> Self {
+        Self {
+            state: StdMutex::new(SemState {
+                count: initial,
+                waiters: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Toma un permiso, bloqueando al hilo `current_tid` hasta que haya uno.
+    ///
+    /// La espera es un punto de cancelación: un hilo cancelado mientras espera
+    /// sale de la cola sin tomar el permiso.
+    pub fn wait(&self, current_tid: MyThreadId) {
+        {
+            let mut st = lock_state(&self.state);
+            if st.count > 0 {
+                st.count -= 1;
+                return;
+            }
+            st.waiters.push_back(current_tid);
+        }
+
+        loop {
+            if !block_current(true) {
+                // fuera de un hilo de la biblioteca no se puede bloquear: esperamos activamente
+                lock_state(&self.state).waiters.retain(|&w| w != current_tid);
+                while !self.try_wait() {
+                    std::thread::yield_now();
+                }
+                return;
+            }
+
+            // el `post` que nos despertó ya nos entregó su permiso
+            let mut st = lock_state(&self.state);
+            if !st.waiters.contains(&current_tid) {
+                return;
+            }
+
+            if cancel_pending() {
+                st.waiters.retain(|&w| w != current_tid);
+                drop(st);
+                my_thread_testcancel();
+            }
+        }
+    }
+
+    /// Intenta tomar un permiso **sin bloquear**.
+    ///
+    /// Devuelve `true` si lo obtuvo, `false` si no había permisos.
+    pub fn try_wait(&self) -> bool {
+        let mut st = lock_state(&self.state);
+        if st.count > 0 {
+            st.count -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Devuelve un permiso, despertando a un hilo en espera si lo hay.
+    pub fn post(&self) {
+        let next = {
+            let mut st = lock_state(&self.state);
+            match st.waiters.pop_front() {
+                Some(tid) => Some(tid),
+                None => {
+                    st.count += 1;
+                    None
+                }
+            }
+        };
+        if let Some(tid) = next {
+            wake_thread(tid);
+        }
+    }
+
+    /// Permisos disponibles en este momento.
+    pub fn value(&self) -> usize {
+        lock_state(&self.state).count
+    }
+
+    /// "Destruye" el semáforo: olvida a los hilos que estuvieran esperando.
+    pub fn destroy(&self) {
+        lock_state(&self.state).waiters.clear();
+    }
+}
+
+/// Función de conveniencia que crea un semáforo con `initial` permisos.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_sem_init, my_sem_trywait, my_sem_post};
+///
+/// let lanes = my_sem_init(2);
+/// assert!(my_sem_trywait(&lanes));
+/// assert!(my_sem_trywait(&lanes));
+/// assert!(!my_sem_trywait(&lanes)); // puente lleno
+/// my_sem_post(&lanes);
+/// assert!(my_sem_trywait(&lanes));
+/// ```
+pub fn my_sem_init(initial: usize) -> MySemaphore {
+    MySemaphore::new(initial)
+}
+
+/// Función de conveniencia que limpia el semáforo.
+pub fn my_sem_destroy(sem: &MySemaphore) {
+    sem.destroy();
+}
+
+/// Envoltorio para `MySemaphore::wait`.
+pub fn my_sem_wait(sem: &MySemaphore, current_tid: MyThreadId) {
+    sem.wait(current_tid);
+}
+
+/// Envoltorio para `MySemaphore::post`.
+pub fn my_sem_post(sem: &MySemaphore) {
+    sem.post();
+}
+
+/// Envoltorio para `MySemaphore::try_wait`.
+pub fn my_sem_trywait(sem: &MySemaphore) -> bool {
+    sem.try_wait()
+}
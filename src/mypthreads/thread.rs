@@ -1,15 +1,50 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Mutex, Arc};
 //use std::time::{SystemTime, UNIX_EPOCH};  //Importa tipos del módulo estándar de tiempo en Rust.
-use crate::scheduler;
-use once_cell::sync::Lazy;
+use crate::scheduler::{self, RtPolicy};
+use crate::mypthreads::context::{self, Context, Stack, DEFAULT_STACK_SIZE};
+use crate::mypthreads::runtime;
+use crate::mypthreads::deadlock::{self, BlockedOn, WaitEdge};
+use crate::mypthreads::MyThreadError;
 
 // =========================
 // Tipos básicos y estados
 // =========================
 
 /// Identificador lógico de un hilo dentro de nuestra biblioteca.
-/// Es simplemente el índice dentro de la tabla global de hilos.
-pub type MyThreadId = usize;
+///
+/// Combina la posición (*slot*) del hilo en la tabla global con la generación
+/// de ese slot. Cuando un hilo terminado se recicla, su slot cambia de
+/// generación, así que un ID viejo ya no coincide con el hilo que ocupe el
+/// slot después y la biblioteca lo rechaza con `NoSuchThread`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MyThreadId {
+    index: usize,
+    generation: u32,
+}
+
+impl MyThreadId {
+    /// Posición del hilo en la tabla.
+    pub fn index(self) -> usize {
+        self.index
+    }
+
+    /// Generación del slot cuando se creó el hilo.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for MyThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.index, self.generation)
+    }
+}
 
 /// Estado en el que puede estar un hilo.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,11 +56,83 @@ pub enum ThreadState {
 }
 
 /// Tipos de scheduler soportados por la biblioteca.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SchedulerType {
     RoundRobin,
     Lottery,
+    /// Reparto proporcional a los `tickets`, como Lottery, pero determinista.
+    Stride,
+    /// Colas multinivel con retroalimentación: los hilos que gastan su
+    /// quantum bajan de nivel y los que ceden pronto se quedan arriba.
+    Mlfq,
+    /// Completely Fair: gana el de menor `vruntime`, que avanza más lento
+    /// cuanto menor es su `nice`.
+    Cfs,
+    /// EDF: gana el de `deadline_ms` más cercano; los que no tienen deadline
+    /// van después, en orden de ID. Con `RtParams` el hilo es periódico.
     RealTime,
+    /// Clase definida por el usuario; su política se registra con
+    /// `scheduler::register_policy`.
+    Custom(u8),
+}
+
+/// Parámetros de una tarea periódica de tiempo real (`my_thread_set_rt_params`).
+///
+/// Cada `period_ms` se libera un trabajo nuevo, que debe terminar (llamar a
+/// `my_thread_wait_next_period`) antes de `relative_deadline_ms` desde su
+/// liberación, y que no usa más de `wcet_ms` de CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtParams {
+    pub period_ms: u64,
+    pub relative_deadline_ms: u64,
+    pub wcet_ms: u64,
+}
+
+impl RtParams {
+    pub fn new(period_ms: u64, relative_deadline_ms: u64, wcet_ms: u64) -> Self {
+        Self { period_ms, relative_deadline_ms, wcet_ms }
+    }
+
+    /// Tarea con deadline implícito (igual al período).
+    pub fn implicit(period_ms: u64, wcet_ms: u64) -> Self {
+        Self::new(period_ms, period_ms, wcet_ms)
+    }
+
+    /// Fracción del CPU que usa la tarea: `wcet / period`.
+    pub fn utilization(&self) -> f64 {
+        self.wcet_ms as f64 / self.period_ms as f64
+    }
+
+    /// Densidad: `wcet / min(deadline, period)`. Igual a la utilización si el
+    /// deadline es implícito.
+    pub fn density(&self) -> f64 {
+        self.wcet_ms as f64 / self.relative_deadline_ms.min(self.period_ms) as f64
+    }
+
+    /// Período, deadline y WCET mayores que 0, y WCET que cabe en el deadline.
+    fn is_valid(&self) -> bool {
+        self.period_ms > 0
+            && self.relative_deadline_ms > 0
+            && self.wcet_ms > 0
+            && self.wcet_ms <= self.relative_deadline_ms
+    }
+}
+
+/// Qué hacer cuando un hilo `RealTime` no cumple su deadline
+/// (`my_thread_set_miss_action`). El incumplimiento se anota siempre en
+/// `scheduler::deadline_miss_events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeadlineMissAction {
+    /// Solo se anota.
+    Ignore,
+    /// Se anota y se avisa por stderr.
+    Log,
+    /// Se anota y se cancela el hilo (`my_thread_cancel`).
+    Abort,
+    /// Se anota y "explota" la planta (`scheduler::plant_exploded`); hasta
+    /// `scheduler::reset_explosion` queda así.
+    #[default]
+    Escalate,
 }
 
 /// Estructura que representa a **un hilo** dentro de la biblioteca.
@@ -36,36 +143,236 @@ pub struct ThreadControlBlock {
     pub state: ThreadState,
     /// Si algún otro hilo está esperando a que este termine (`join`), aquí se guarda su ID.
     pub waiting_thread_id: Option<MyThreadId>,
+    /// Qué está esperando este hilo mientras está BLOCKED (mutex o join).
+    pub blocked_on: Option<BlockedOn>,
     /// Qué scheduler se debe usar para este hilo.
     pub scheduler_type: SchedulerType,
     /// Si es `true`, el hilo no se puede esperar (`join`).
     pub detached: bool,
     /// Función que este hilo debe ejecutar cuando se le asigne CPU.
     pub start_routine: Option<Arc<dyn Fn() + Send + Sync>>,
-    // TODO: contexto real más adelante
 
     // Metadatos de scheduling
-    pub tickets: u32, // para Lottery (>=1)
-    pub deadline_ms: Option<u64>, // para RT (epoch ms); None si no aplica
+    pub tickets: u32, // para Lottery y Stride (>=1)
+    /// Prioridad de Cfs, de -20 (más CPU) a 19 (menos); 0 por defecto.
+    pub nice: i8,
+    /// Tiempo de CPU (µs) ponderado por `nice` que lleva el hilo en Cfs.
+    pub vruntime: u64,
+    /// Moneda en que están expresados los `tickets` (`scheduler::set_currency`);
+    /// `None` para la moneda base.
+    pub currency: Option<String>,
+    pub deadline_ms: Option<u64>, // para RT (ms de `scheduler::now_ms`); None si no aplica
+    /// Parámetros de tarea periódica (RT); `None` si el hilo no es periódico.
+    pub rt_params: Option<RtParams>,
+    /// Deadlines que este hilo no cumplió.
+    pub deadline_misses: u64,
+    /// Reacción cuando no cumple un deadline.
+    pub miss_action: DeadlineMissAction,
+    /// Liberación del trabajo actual de un hilo periódico (o del siguiente,
+    /// mientras lo espera), en el reloj del planificador.
+    pub release_ms: Option<u64>,
 
-                      // TODO: contexto real (ucontext_t equivalente)
+    /// Clase y deadline propios del hilo mientras tiene prestados otros por
+    /// herencia de prioridad o techo de un `MyMutex`. En ese caso
+    /// `scheduler_type` y `deadline_ms` guardan los valores prestados, que son
+    /// los que usa el scheduler. `None` si no hay préstamo.
+    pub base_sched: Option<(SchedulerType, Option<u64>)>,
+    /// Prioridad fija prestada con la clase (la usan RM y DM; menor = más
+    /// prioritaria). `None` si no hay préstamo o el préstamo no trae una: vale
+    /// la de `rt_params`.
+    pub fixed_priority: Option<u64>,
+    /// Préstamos vigentes: (ID del mutex, urgencia prestada).
+    boosts: Vec<(usize, Urgency)>,
+
+    /// Registros guardados mientras el hilo no está en CPU.
+    /// Va en `Box` para que su dirección no cambie si la tabla crece.
+    context: Box<Context>,
+    /// Pila privada del hilo; se libera cuando el hilo termina.
+    stack: Option<Stack>,
+    /// Un `wake_thread` llegó antes de que el hilo alcanzara a bloquearse;
+    /// el próximo `block_current` retorna de inmediato.
+    wake_pending: bool,
+    /// Valor de salida (`my_thread_exit` o el retorno de `my_thread_spawn`),
+    /// hasta que alguien lo recoja con `join`.
+    exit_value: Option<Box<dyn Any + Send>>,
+    /// Alguien pidió cancelar el hilo; se cancela en su próximo punto de cancelación.
+    cancel_requested: bool,
+    /// El hilo terminó por cancelación.
+    canceled: bool,
+    /// Último deadline incumplido ya reportado, para reportar cada uno una vez.
+    missed_deadline: Option<u64>,
+    /// Deadline propio con que el hilo está anotado para `sweep_deadlines`.
+    indexed_deadline: Option<u64>,
+    /// Manejadores de limpieza (`my_thread_cleanup_push`), el último arriba.
+    cleanup: Vec<Box<dyn FnOnce() + Send>>,
 }
 
+/// Carga útil con la que `my_thread_exit` desenrolla la pila del hilo hasta
+/// `thread_entry`, que guarda el valor.
+struct ThreadExit(Box<dyn Any + Send>);
+
+/// Carga útil con la que se desenrolla la pila de un hilo cancelado.
+struct ThreadCanceled;
+
 // =========================
 // "Tabla" global de hilos
 // =========================
 
-/// Máximo de hilos que vamos a soportar
-const MAX_THREADS: usize = 64;
+/// Máximo de hilos vivos por defecto (se cambia con `my_thread_set_max_threads`).
+const DEFAULT_MAX_THREADS: usize = 64;
+
+/// Un lugar de la tabla. `tcb` es `None` mientras el slot está libre.
+struct Slot {
+    generation: u32,
+    tcb: Option<ThreadControlBlock>,
+    /// Último hilo de este slot recogido con `join`: su generación y quién lo
+    /// recogió (`None` si fue desde fuera de los hilos de la biblioteca).
+    joined: Option<(u32, Option<MyThreadId>)>,
+}
+
+/// Tabla de hilos con slots reutilizables.
+///
+/// Un hilo ocupa su slot hasta que termina y ya nadie lo va a esperar: era
+/// *detached* o alguien hizo `join` sobre él. Ahí el slot queda libre para el
+/// próximo `my_thread_create`, con una generación nueva.
+pub struct ThreadTable {
+    slots: Vec<Slot>,
+    /// Índices de los slots libres.
+    free: Vec<usize>,
+    /// Máximo de hilos vivos (no terminados y sin reciclar) a la vez.
+    max_threads: usize,
+}
+
+impl ThreadTable {
+    pub(super) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            max_threads: DEFAULT_MAX_THREADS,
+        }
+    }
+
+    /// TCB del hilo `tid`, o `None` si no existe o ya se recicló.
+    pub fn get(&self, tid: MyThreadId) -> Option<&ThreadControlBlock> {
+        self.slots
+            .get(tid.index)
+            .filter(|slot| slot.generation == tid.generation)
+            .and_then(|slot| slot.tcb.as_ref())
+    }
+
+    /// Como `get`, pero mutable.
+    pub fn get_mut(&mut self, tid: MyThreadId) -> Option<&mut ThreadControlBlock> {
+        self.slots
+            .get_mut(tid.index)
+            .filter(|slot| slot.generation == tid.generation)
+            .and_then(|slot| slot.tcb.as_mut())
+    }
+
+    /// `true` si `tid` corresponde a un hilo que sigue en la tabla.
+    pub fn contains(&self, tid: MyThreadId) -> bool {
+        self.get(tid).is_some()
+    }
+
+    /// Recorre los hilos que están en la tabla, en orden de slot.
+    pub fn iter(&self) -> impl Iterator<Item = &ThreadControlBlock> {
+        self.slots.iter().filter_map(|slot| slot.tcb.as_ref())
+    }
+
+    /// Como `iter`, pero mutable.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut ThreadControlBlock> {
+        self.slots.iter_mut().filter_map(|slot| slot.tcb.as_mut())
+    }
+
+    /// Cantidad de hilos en la tabla (incluye terminados que nadie ha recogido).
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// `true` si no hay hilos en la tabla.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Máximo de hilos que puede haber en la tabla a la vez.
+    pub fn max_threads(&self) -> usize {
+        self.max_threads
+    }
+
+    /// Guarda un hilo nuevo en un slot libre (o en uno nuevo) y devuelve su ID.
+    fn insert(
+        &mut self,
+        make: impl FnOnce(MyThreadId) -> ThreadControlBlock,
+    ) -> Result<MyThreadId, MyThreadError> {
+        if self.len() >= self.max_threads {
+            return Err(MyThreadError::MaxThreadsReached { max: self.max_threads });
+        }
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, tcb: None, joined: None });
+                self.slots.len() - 1
+            }
+        };
+        let slot = &mut self.slots[index];
+        let tid = MyThreadId { index, generation: slot.generation };
+        slot.tcb = Some(make(tid));
+        Ok(tid)
+    }
+
+    /// Saca al hilo `tid` de la tabla y libera su slot con una generación nueva.
+    ///
+    /// El TCB se devuelve para soltarlo **fuera** del lock de la tabla: su
+    /// `start_routine` o su valor de salida pueden tener destructores que usen
+    /// la biblioteca.
+    fn remove(&mut self, tid: MyThreadId) -> Option<ThreadControlBlock> {
+        self.get(tid)?;
+        let slot = &mut self.slots[tid.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(tid.index);
+        slot.tcb.take()
+    }
+
+    /// Como `remove`, pero recuerda que `waiter` recogió a `tid` con `join`,
+    /// para que un segundo `join` sobre el mismo ID no parezca un ID inválido.
+    fn remove_joined(
+        &mut self,
+        tid: MyThreadId,
+        waiter: Option<MyThreadId>,
+    ) -> Option<ThreadControlBlock> {
+        let tcb = self.remove(tid)?;
+        self.slots[tid.index].joined = Some((tid.generation, waiter));
+        Some(tcb)
+    }
+
+    /// Quién recogió con `join` a `tid`, si fue el último recogido de su slot.
+    fn joined_by(&self, tid: MyThreadId) -> Option<Option<MyThreadId>> {
+        let (generation, waiter) = self.slots.get(tid.index)?.joined?;
+        (generation == tid.generation).then_some(waiter)
+    }
+}
+
+impl Index<MyThreadId> for ThreadTable {
+    type Output = ThreadControlBlock;
 
-/// Tabla global donde se guardan **todos** los hilos creados.
-/// Se protege con `Mutex` porque todos los hilos van a tocarla.
-static THREAD_TABLE: Lazy<Mutex<Vec<ThreadControlBlock>>> =
-    Lazy::new(|| Mutex::new(Vec::with_capacity(MAX_THREADS)));
+    fn index(&self, tid: MyThreadId) -> &ThreadControlBlock {
+        self.get(tid).expect("ID de hilo inválido o reciclado")
+    }
+}
+
+impl IndexMut<MyThreadId> for ThreadTable {
+    fn index_mut(&mut self, tid: MyThreadId) -> &mut ThreadControlBlock {
+        self.get_mut(tid).expect("ID de hilo inválido o reciclado")
+    }
+}
 
-/// ID del hilo que está actualmente en ejecución.
-/// Si es `None` significa que no hay hilo corriendo (por ejemplo, ya terminaron todos).
-static CURRENT_THREAD_ID: Lazy<Mutex<Option<MyThreadId>>> = Lazy::new(|| Mutex::new(None));
+// La tabla, el hilo actual y el contexto del planificador son del runtime
+// actual (`runtime::current()`); ver `mypthreads::Runtime`.
+
+thread_local! {
+    /// `true` en el hilo del SO que está ejecutando `my_thread_run`.
+    /// Solo desde ahí tiene sentido cambiar de contexto.
+    static DISPATCHER_ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
 
 // =========================
 // Funciones de hilos
@@ -73,10 +380,13 @@ static CURRENT_THREAD_ID: Lazy<Mutex<Option<MyThreadId>>> = Lazy::new(|| Mutex::
 
 /// Crea un nuevo hilo dentro de la biblioteca.
 ///
-/// - `start_routine`: función que debería ejecutar el hilo (por ahora solo la guardamos).
+/// - `start_routine`: función que ejecuta el hilo, sobre su propia pila.
 /// - `scheduler_type`: con qué scheduler se va a planificar este hilo.
 ///
-/// Devuelve el ID del hilo creado o un error si se alcanzó el máximo.
+/// El hilo queda READY; empieza a correr cuando `my_thread_run` lo despache.
+///
+/// Devuelve el ID del hilo creado o un error si ya hay
+/// `my_thread_max_threads()` hilos en la tabla.
 ///
 /// ```rust
 /// use proyecto1::mypthreads::{my_thread_create, SchedulerType};
@@ -92,47 +402,179 @@ static CURRENT_THREAD_ID: Lazy<Mutex<Option<MyThreadId>>> = Lazy::new(|| Mutex::
 pub fn my_thread_create<F>(
     start_routine: F, // puede ser fn o closure
     scheduler_type: SchedulerType,
-) -> Result<MyThreadId, &'static str>
+) -> Result<MyThreadId, MyThreadError>
 where 
     F: Fn() + Send + Sync + 'static,
 {
+    let rt = runtime::current();
+
+    let mut table = rt.threads.lock().unwrap();
+
+    // Guardamos el hilo en la tabla global (reusando un slot libre si hay)
+    let id = table.insert(|id| {
+        let stack = Stack::new(DEFAULT_STACK_SIZE);
+        let context = Box::new(Context::new(&stack, thread_entry));
+
+        ThreadControlBlock {
+            id,
+            state: ThreadState::Ready,
+            waiting_thread_id: None,
+            blocked_on: None,
+            scheduler_type,
+            detached: false,
+            start_routine: Some(Arc::new(start_routine)),
 
-    let mut table = THREAD_TABLE.lock().unwrap();
+            // Defaults de las propiedades para schedule
+            tickets: 1,
+            nice: 0,
+            vruntime: 0,
+            currency: None,
+            deadline_ms: None,
+            rt_params: None,
+            release_ms: None,
+            deadline_misses: 0,
+            miss_action: DeadlineMissAction::default(),
+            base_sched: None,
+            fixed_priority: None,
+            boosts: Vec::new(),
 
-    if table.len() >= MAX_THREADS {
-        return Err("max threads reached");
+            context,
+            stack: Some(stack),
+            wake_pending: false,
+            exit_value: None,
+            cancel_requested: false,
+            missed_deadline: None,
+            indexed_deadline: None,
+            canceled: false,
+            cleanup: Vec::new(),
+        }
+    })?;
+
+    // registrar en la cola de su clase
+    enqueue(&mut table[id]);
+
+    Ok(id)
+}
+
+/// Manejador tipado de un hilo creado con `my_thread_spawn`: su `join()`
+/// devuelve el valor con que terminó el hilo.
+#[derive(Debug)]
+pub struct MyJoinHandle<T> {
+    tid: MyThreadId,
+    _result: PhantomData<fn() -> T>,
+}
+
+impl<T: Any + Send> MyJoinHandle<T> {
+    /// ID del hilo.
+    pub fn id(&self) -> MyThreadId {
+        self.tid
     }
 
-    let id = table.len();
+    /// Espera a que el hilo termine y devuelve su valor.
+    ///
+    /// Si el hilo fue cancelado devuelve `Canceled`; si terminó sin un valor
+    /// de tipo `T` (por un panic, o por `my_thread_exit` con otro tipo),
+    /// devuelve `MissingExitValue`.
+    pub fn join(self) -> Result<T, MyThreadError> {
+        let joined = join_and_reclaim(self.tid)?;
+        if joined.canceled {
+            return Err(MyThreadError::Canceled(self.tid));
+        }
+        joined
+            .value
+            .and_then(|v| v.downcast::<T>().ok())
+            .map(|v| *v)
+            .ok_or(MyThreadError::MissingExitValue(self.tid))
+    }
 
-    let tcb = ThreadControlBlock {
-        id,
-        state: ThreadState::Ready,
-        waiting_thread_id: None,
-        scheduler_type,
-        detached: false,
-        start_routine: Some(Arc::new(start_routine)),
+    /// Suelta el hilo: ya nadie va a recoger su valor.
+    pub fn detach(self) -> Result<(), MyThreadError> {
+        my_thread_detach(self.tid)
+    }
+}
 
-        // Defaults de las propiedades para schedule
-        tickets: 1,
-        deadline_ms: None,
-    };
+/// Crea un hilo que devuelve un valor, como `pthread_create` + `pthread_exit(value)`.
+///
+/// El valor que retorne `start_routine` (o el que pase a `my_thread_exit`)
+/// se recoge con `MyJoinHandle::join`.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_thread_run, my_thread_spawn, SchedulerType};
+///
+/// let worker = my_thread_spawn(|| 6 * 7, SchedulerType::RoundRobin).unwrap();
+/// let coordinator = my_thread_spawn(
+///     move || worker.join().unwrap() + 1,
+///     SchedulerType::RoundRobin,
+/// ).unwrap();
+///
+/// my_thread_run();
+/// assert_eq!(coordinator.join().unwrap(), 43);
+/// ```
+pub fn my_thread_spawn<F, T>(
+    start_routine: F,
+    scheduler_type: SchedulerType,
+) -> Result<MyJoinHandle<T>, MyThreadError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Any + Send + 'static,
+{
+    // `start_routine` de la tabla es `Fn`; la de aquí se consume una sola vez
+    let routine = Mutex::new(Some(start_routine));
+    let tid = my_thread_create(
+        move || {
+            if let Some(f) = routine.lock().unwrap().take() {
+                let value = f();
+                set_exit_value(Box::new(value));
+            }
+        },
+        scheduler_type,
+    )?;
 
-    // Guardamos el hilo en la tabla global
-    table.push(tcb);
+    Ok(MyJoinHandle {
+        tid,
+        _result: PhantomData,
+    })
+}
 
-    // registrar en scheduler
-    scheduler::scheduler_add(id);
+/// Termina el hilo actual en este punto con `value` como valor de salida,
+/// como `pthread_exit(value)`. Quien haga `join` lo recibe.
+///
+/// La pila del hilo se desenrolla hasta su punto de entrada, así que los
+/// destructores de las variables locales sí se ejecutan.
+///
+/// # Panics
+/// Si se llama fuera de un hilo de la biblioteca.
+pub fn my_thread_exit<T: Any + Send>(value: T) -> ! {
+    let rt = runtime::current();
+    let in_thread = DISPATCHER_ACTIVE.with(Cell::get) && rt.current.lock().unwrap().is_some();
+    if !in_thread {
+        panic!("my_thread_exit called outside a mypthreads thread");
+    }
+    panic::resume_unwind(Box::new(ThreadExit(Box::new(value))));
+}
 
-    Ok(id)
+/// Guarda el valor de salida del hilo actual.
+fn set_exit_value(value: Box<dyn Any + Send>) {
+    let rt = runtime::current();
+    let current_id = *rt.current.lock().unwrap();
+    if let Some(tid) = current_id {
+        rt.threads.lock().unwrap()[tid].exit_value = Some(value);
+    }
 }
 
-/// Marca el hilo actual como **terminado** y permite que el scheduler
-/// escoja el siguiente. También despierta a un hilo que estuviera haciendo `join`
-/// sobre este.
+/// Marca el hilo actual como **terminado** y devuelve el control al planificador,
+/// que escoge el siguiente. También despierta a un hilo que estuviera haciendo
+/// `join` sobre este.
+///
+/// Llamada desde un hilo de la biblioteca no retorna nunca.
 pub fn my_thread_end() {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    let mut current_id_lock = CURRENT_THREAD_ID.lock().unwrap();
+    let rt = runtime::current();
+    if !DISPATCHER_ACTIVE.with(Cell::get) {
+        return;
+    }
+
+    let mut table = rt.threads.lock().unwrap();
+    let current_id_lock = rt.current.lock().unwrap();
 
     // Si no hay hilo actual, no hay nada que hacer
     let Some(current_id) = *current_id_lock else {
@@ -146,6 +588,7 @@ pub fn my_thread_end() {
         // scope para no tener 2 préstamos mutables a la vez
         let current = &mut table[current_id];
         current.state = ThreadState::Finished;
+        index_deadline(current);
         // si alguien estaba esperando este hilo, lo anotamos
         waiter_id_opt = current.waiting_thread_id;
         current.waiting_thread_id = None;
@@ -153,139 +596,926 @@ pub fn my_thread_end() {
 
     // si había alguien esperando, lo pasamos a READY
     if let Some(waiter_id) = waiter_id_opt {
-        if let Some(waiter) = table.get_mut(waiter_id) {
-            waiter.state = ThreadState::Ready;
-        }
+        make_ready(&mut table, waiter_id);
     }
 
-    // pedir el siguiente hilo al scheduler
-    if let Some(next_id) = scheduler::scheduler_next() {
-        if let Some(next) = table.get_mut(next_id) {
-            next.state = ThreadState::Running;
-        }
-        *current_id_lock = Some(next_id);
-    } else {
-        *current_id_lock = None;
-    }
+    drop(current_id_lock);
+    drop(table);
+    // esta pila no se desenrolla nunca: no dejar referencias al runtime en ella
+    drop(rt);
+
+    // volver al planificador; este contexto ya no se reanuda
+    switch_to_scheduler(current_id);
+    unreachable!("se reanudó un hilo terminado");
 }
 
 /// Cede voluntariamente el procesador a otro hilo según el scheduler.
+///
+/// El hilo actual queda READY y se suspende aquí mismo; continúa después de
+/// esta llamada cuando el scheduler lo vuelva a elegir.
+///
+/// Es un punto de cancelación.
 pub fn my_thread_yield_() {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    let mut current_id_lock = CURRENT_THREAD_ID.lock().unwrap();
+    my_thread_testcancel();
+    let rt = runtime::current();
+    if !DISPATCHER_ACTIVE.with(Cell::get) {
+        return;
+    }
+
+    let mut table = rt.threads.lock().unwrap();
+    let current_id_lock = rt.current.lock().unwrap();
 
     // si no hay hilo actual, nada que hacer
     let Some(current_id) = *current_id_lock else {
         return;
     };
 
-    // pedir otro hilo al scheduler
-    let Some(next_id) = scheduler::scheduler_next() else {
-        return;
-    };
-
-    // si el scheduler devolvió el mismo hilo, no hacemos nada
-    if next_id == current_id {
-        return;
-    }
-
     // actual pasa a READY
     if let Some(current) = table.get_mut(current_id) {
         current.state = ThreadState::Ready;
     }
 
-    // el otro pasa a RUNNING
-    if let Some(next) = table.get_mut(next_id) {
-        next.state = ThreadState::Running;
-    }
+    drop(current_id_lock);
+    drop(table);
+    drop(rt);
 
-    // actualizamos el hilo actual
-    *current_id_lock = Some(next_id);
-    // TODO: aquí iría el cambio de contexto real
+    switch_to_scheduler(current_id);
+    my_thread_testcancel();
+}
+
+/// Punto de expropiación: si el modo expropiativo está activo y el hilo
+/// actual ya agotó su quantum, cede el CPU como `my_thread_yield_`.
+///
+/// Los ciclos largos que no llaman a la biblioteca deberían llamarla de vez
+/// en cuando para poder ser repartidos en el tiempo.
+pub fn my_thread_preempt_point() {
+    scheduler::clock_tick();
+    if scheduler::quantum_expired() {
+        my_thread_yield_();
+    }
 }
 
 /// Bloquea el hilo actual hasta que el hilo con ID `target_id` termine.
 ///
-/// Si el hilo ya había terminado, devuelve `Ok(())` inmediato.
+/// Si el hilo ya había terminado, devuelve `Ok(())` inmediato. Si no, el hilo
+/// actual queda BLOCKED (sin consumir CPU) y `my_thread_end` del hilo esperado
+/// lo vuelve a poner READY.
+///
+/// Después del `join` el slot del hilo esperado se recicla y su ID deja de
+/// ser válido: un segundo `join` devuelve `AlreadyJoined` (mientras el slot
+/// no vuelva a recogerse con otro `join`; después, `NoSuchThread`).
 ///
-/// Importante: como todavía no tenemos cambio de contexto real,
-/// esta versión usa un loop con `my_thread_yield_()`.
-pub fn my_thread_join(target_id: MyThreadId) -> Result<(), &'static str> {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    let current_id_lock = CURRENT_THREAD_ID.lock().unwrap();
+/// Errores: `NoSuchThread`, `JoinDetached`, `JoinSelf`, `AlreadyJoined` (otro
+/// hilo ya lo espera o ya lo recogió), `NoCurrentThread` y `Deadlock` (con
+/// detección activa).
+pub fn my_thread_join(target_id: MyThreadId) -> Result<(), MyThreadError> {
+    join_and_reclaim(target_id).map(drop)
+}
+
+/// Como `my_thread_join`, pero además entrega el valor de salida del hilo
+/// (como `pthread_join(tid, &retval)`), si terminó con uno. Un hilo
+/// cancelado no tiene valor.
+pub fn my_thread_join_value(
+    target_id: MyThreadId,
+) -> Result<Option<Box<dyn Any + Send>>, MyThreadError> {
+    join_and_reclaim(target_id).map(|joined| joined.value)
+}
+
+/// Cómo terminó un hilo recogido con `join`.
+struct Joined {
+    value: Option<Box<dyn Any + Send>>,
+    canceled: bool,
+}
+
+/// Espera a `target_id`, lo saca de la tabla y devuelve cómo terminó.
+///
+/// Es un punto de cancelación.
+fn join_and_reclaim(target_id: MyThreadId) -> Result<Joined, MyThreadError> {
+    my_thread_testcancel();
+    wait_for(target_id)?;
+
+    let rt = runtime::current();
+    let finished = {
+        let mut table = rt.threads.lock().unwrap();
+        let waiter = *rt.current.lock().unwrap();
+        table.remove_joined(target_id, waiter)
+    };
+    // el TCB se suelta aquí, sin el lock de la tabla
+    Ok(match finished {
+        Some(mut t) => Joined { value: t.exit_value.take(), canceled: t.canceled },
+        None => Joined { value: None, canceled: false },
+    })
+}
+
+/// Parte de `my_thread_join` que espera a que `target_id` termine.
+fn wait_for(target_id: MyThreadId) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let current_id_lock = rt.current.lock().unwrap();
+
+    // validar que el hilo exista (y que el ID no sea de un slot reciclado)
+    let Some(target) = table.get(target_id) else {
+        return Err(match table.joined_by(target_id) {
+            Some(waiter) => MyThreadError::AlreadyJoined { target: target_id, waiter },
+            None => MyThreadError::NoSuchThread(target_id),
+        });
+    };
 
-    // validar que el hilo exista
-    if target_id >= table.len() {
-        return Err("thread does not exist");
+    // un hilo detached no se puede esperar
+    if target.detached {
+        return Err(MyThreadError::JoinDetached(target_id));
     }
 
     // si ya terminó, nada que esperar
-    if table[target_id].state == ThreadState::Finished {
+    if target.state == ThreadState::Finished {
         return Ok(());
     }
 
     // quién soy yo?
     let Some(current_id) = *current_id_lock else {
-        return Err("no current thread");
+        return Err(MyThreadError::NoCurrentThread);
     };
 
-    // marco que el target me despierte cuando termine
-    table[target_id].waiting_thread_id = Some(current_id);
+    if target_id == current_id {
+        return Err(MyThreadError::JoinSelf(current_id));
+    }
 
-    // bloquearme yo
-    if let Some(me) = table.get_mut(current_id) {
-        me.state = ThreadState::Blocked;
+    // solo un hilo puede esperar a otro
+    if let Some(waiter) = table[target_id].waiting_thread_id {
+        return Err(MyThreadError::AlreadyJoined { target: target_id, waiter: Some(waiter) });
     }
 
-    // soltar locks ANTES del loop
+    // con detección activa, no esperar si eso cierra un ciclo
+    let edge = WaitEdge::Join { waiter: current_id, target: target_id };
+    if deadlock::would_deadlock(&table, edge) {
+        return Err(MyThreadError::Deadlock(current_id));
+    }
+
+    // marco que el target me despierte cuando termine
+    table[target_id].waiting_thread_id = Some(current_id);
+    table[current_id].blocked_on = Some(BlockedOn::Join(target_id));
+    deadlock::set_waiting(current_id, Some(BlockedOn::Join(target_id)));
+
+    // soltar locks ANTES de cambiar de contexto
     drop(table);
     drop(current_id_lock);
+    drop(rt);
 
-    // esperar cooperativamente
+    // dormir hasta que el target termine
     loop {
         {
-            let table = THREAD_TABLE.lock().unwrap();
-            if table[target_id].state == ThreadState::Finished {
+            let rt = runtime::current();
+            let mut table = rt.threads.lock().unwrap();
+            let finished = table
+                .get(target_id)
+                .is_none_or(|t| t.state == ThreadState::Finished);
+            if finished {
                 break;
             }
+            table[current_id].state = ThreadState::Blocked;
+        }
+        switch_to_scheduler(current_id);
+
+        // nos despertó una cancelación: dejar de esperar antes de desenrollar
+        if cancel_pending() {
+            let rt = runtime::current();
+            let mut table = rt.threads.lock().unwrap();
+            if let Some(target) = table.get_mut(target_id) {
+                if target.waiting_thread_id == Some(current_id) {
+                    target.waiting_thread_id = None;
+                }
+            }
+            table[current_id].blocked_on = None;
+            deadlock::set_waiting(current_id, None);
+            drop(table);
+            drop(rt);
+            my_thread_testcancel();
         }
-        my_thread_yield_();
     }
 
+    set_blocked_on(current_id, None);
     Ok(())
 }
 
+/// Duerme el hilo actual durante `ms` milisegundos sin detener a los demás.
+///
+/// Ver `my_thread_sleep_until`.
+pub fn my_thread_sleep(ms: u64) {
+    my_thread_sleep_until(scheduler::now_ms().saturating_add(ms));
+}
+
+/// Duerme el hilo actual hasta el instante `deadline_ms` del reloj del
+/// planificador (`scheduler::now_ms`).
+///
+/// El hilo queda BLOCKED y el CPU pasa a otros hilos; `scheduler_next` lo
+/// vuelve a poner READY cuando llega la hora. A diferencia de
+/// `std::thread::sleep`, no detiene al hilo del SO ni a los demás hilos de la
+/// biblioteca. Es un punto de cancelación.
+///
+/// Fuera de un hilo de la biblioteca duerme al hilo del SO (o, con el reloj
+/// simulado, solo adelanta el reloj).
+pub fn my_thread_sleep_until(deadline_ms: u64) {
+    my_thread_testcancel();
+    let current_id = {
+        let rt = runtime::current();
+        let current_id = *rt.current.lock().unwrap();
+        current_id
+    };
+    let Some(current_id) = current_id.filter(|_| DISPATCHER_ACTIVE.with(Cell::get)) else {
+        scheduler::wait_until(deadline_ms);
+        return;
+    };
+
+    while scheduler::now_ms() < deadline_ms {
+        set_blocked_on(current_id, Some(BlockedOn::Sleep(deadline_ms)));
+        block_current(true);
+        if cancel_pending() {
+            set_blocked_on(current_id, None);
+            my_thread_testcancel();
+        }
+    }
+    set_blocked_on(current_id, None);
+}
+
+/// Reporta cada deadline RT vencido a `now_ms` una vez y aplica la reacción de su hilo.
+pub(crate) fn sweep_deadlines(now_ms: u64) {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let mut misses = Vec::new();
+    for (tid, deadline) in scheduler::take_due_deadlines(now_ms) {
+        let Some(t) = table.get_mut(tid) else {
+            continue;
+        };
+        t.indexed_deadline = None;
+        let Some(miss) = note_deadline_miss(t, deadline, now_ms) else {
+            continue;
+        };
+        if miss.action == DeadlineMissAction::Abort {
+            // si estaba bloqueado, despierta para cancelarse
+            make_ready(&mut table, tid);
+        }
+        misses.push(miss);
+    }
+    drop(table);
+    misses.iter().for_each(scheduler::log_deadline_miss);
+}
+
+/// Mantiene anotado el deadline propio de `t` para `sweep_deadlines`: el de
+/// un hilo `RealTime` sin terminar que todavía no se reportó.
+fn index_deadline(t: &mut ThreadControlBlock) {
+    let deadline = match base_params(t) {
+        (SchedulerType::RealTime, Some(deadline))
+            if t.state != ThreadState::Finished && t.missed_deadline != Some(deadline) =>
+        {
+            Some(deadline)
+        }
+        _ => None,
+    };
+    if t.indexed_deadline == deadline {
+        return;
+    }
+    if let Some(old) = t.indexed_deadline {
+        scheduler::remove_deadline(t.id, old);
+    }
+    if let Some(deadline) = deadline {
+        scheduler::add_deadline(t.id, deadline);
+    }
+    t.indexed_deadline = deadline;
+}
+
+/// Anota que `t` no cumplió `deadline` (si no estaba anotado ya) y aplica su
+/// `miss_action`. Devuelve el incumplimiento nuevo para que quien llama lo
+/// avise (`scheduler::log_deadline_miss`) después de soltar la tabla.
+fn note_deadline_miss(
+    t: &mut ThreadControlBlock,
+    deadline: u64,
+    now_ms: u64,
+) -> Option<scheduler::DeadlineMiss> {
+    if t.missed_deadline == Some(deadline) {
+        return None;
+    }
+    t.missed_deadline = Some(deadline);
+    t.deadline_misses += 1;
+    index_deadline(t);
+    let miss = scheduler::DeadlineMiss {
+        tid: t.id,
+        deadline_ms: deadline,
+        lateness_ms: now_ms.saturating_sub(deadline),
+        at_ms: now_ms,
+        action: t.miss_action,
+    };
+    scheduler::record_deadline_miss(miss);
+    if t.miss_action == DeadlineMissAction::Abort {
+        t.cancel_requested = true;
+    }
+    Some(miss)
+}
+
+/// Pasa a READY a los hilos dormidos cuya hora ya llegó.
+///
+/// Devuelve el instante en que despierta el próximo de los que siguen
+/// dormidos, si hay alguno.
+pub(crate) fn wake_sleepers(now_ms: u64) -> Option<u64> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let (due, next_wake) = scheduler::take_due_sleepers(now_ms);
+    for tid in due {
+        make_ready(&mut table, tid);
+    }
+    next_wake
+}
+
+/// Ejecuta el planificador en el hilo del SO que lo llama.
+///
+/// Despacha hilos READY (cambiando de contexto hacia ellos) hasta que ya no
+/// quede ninguno listo: todos terminaron o los que quedan están bloqueados.
+/// Si los únicos que quedan están dormidos (`my_thread_sleep`), espera a que
+/// despierte el primero.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_thread_create, my_thread_run, my_thread_yield_, SchedulerType};
+///
+/// for name in ["a", "b"] {
+///     my_thread_create(move || {
+///         for i in 0..3 {
+///             println!("{name}{i}");
+///             my_thread_yield_();
+///         }
+///     }, SchedulerType::RoundRobin).unwrap();
+/// }
+/// my_thread_run(); // a0 b0 a1 b1 a2 b2
+/// ```
+pub fn my_thread_run() {
+    DISPATCHER_ACTIVE.with(|d| d.set(true));
+    // avisa con `SIGALRM` cuando un hilo agota su quantum (modo expropiativo)
+    let timer = scheduler::Timer::start(runtime::current().share());
+
+    loop {
+        if let Some(next_id) = scheduler::scheduler_next() {
+            dispatch(next_id);
+            continue;
+        }
+        // nadie listo: si alguien duerme, esperar (o saltar, con el reloj
+        // simulado) a que despierte
+        let Some(wake_at) = wake_sleepers(scheduler::now_ms()) else {
+            break;
+        };
+        scheduler::wait_until(wake_at);
+    }
+
+    drop(timer);
+    DISPATCHER_ACTIVE.with(|d| d.set(false));
+}
+
+/// Cambia del planificador al hilo `tid` y vuelve cuando ese hilo cede,
+/// se bloquea o termina.
+fn dispatch(tid: MyThreadId) {
+    let rt = runtime::current();
+    let (to, from) = {
+        let mut table = rt.threads.lock().unwrap();
+        let mut current_id_lock = rt.current.lock().unwrap();
+        let mut sched_ctx = rt.scheduler_context.lock().unwrap();
+
+        let Some(next) = table.get_mut(tid) else {
+            return;
+        };
+        next.state = ThreadState::Running;
+        *current_id_lock = Some(tid);
+        if next.scheduler_type == SchedulerType::Cfs {
+            scheduler::cfs_observe(next.vruntime);
+        }
+        scheduler::notify_dequeue(tid, next.scheduler_type);
+        scheduler::start_slice(next.scheduler_type);
+
+        (
+            &*next.context as *const Context,
+            &mut **sched_ctx as *mut Context,
+        )
+    };
+
+    let depth = scheduler::switch_depth();
+    // SAFETY: ambos contextos viven en `Box` que no se mueven, y no hay
+    // locks tomados durante el cambio.
+    unsafe { context::switch(from, to) };
+    scheduler::restore_depth(depth);
+
+    *rt.current.lock().unwrap() = None;
+
+    // avisar a la política según cómo soltó el CPU
+    let ran_ms = scheduler::end_slice();
+    let mut table = rt.threads.lock().unwrap();
+    let mut reclaim = false;
+    if let Some(tcb) = table.get_mut(tid) {
+        if tcb.scheduler_type == SchedulerType::Cfs {
+            tcb.vruntime += scheduler::cfs_charge(ran_ms, tcb.nice);
+        }
+        scheduler::notify_tick(tid, tcb.scheduler_type, ran_ms);
+        match tcb.state {
+            ThreadState::Ready => enqueue(tcb),
+            ThreadState::Blocked => scheduler::notify_block(tid, tcb.scheduler_type),
+            // si el hilo terminó, ya nadie va a usar su pila
+            ThreadState::Finished => {
+                scheduler::notify_exit(tid, tcb.scheduler_type);
+                tcb.stack = None;
+                reclaim = tcb.detached;
+            }
+            ThreadState::Running => {}
+        }
+    }
+
+    // un hilo detached terminado ya no le interesa a nadie: liberar su slot
+    if reclaim {
+        let finished = table.remove(tid);
+        drop(table);
+        drop(finished);
+    }
+}
+
+/// Bloquea el hilo actual y vuelve al planificador; retorna cuando otro hilo
+/// lo despierte con `wake_thread`.
+///
+/// El llamador no debe tener locks tomados. Devuelve `false` (sin bloquear)
+/// si no se está ejecutando dentro de un hilo de la biblioteca.
+///
+/// Si la espera es `cancelable`, con una cancelación pendiente retorna sin
+/// bloquear: el llamador debe revisar `cancel_pending` para distinguirlo de
+/// un despertar normal. Si no, se bloquea igual y la cancelación queda
+/// pendiente para el próximo punto de cancelación.
+pub(crate) fn block_current(cancelable: bool) -> bool {
+    let rt = runtime::current();
+    if !DISPATCHER_ACTIVE.with(Cell::get) {
+        return false;
+    }
+    let Some(current_id) = *rt.current.lock().unwrap() else {
+        return false;
+    };
+    {
+        let mut table = rt.threads.lock().unwrap();
+        let me = &mut table[current_id];
+        if me.wake_pending {
+            me.wake_pending = false;
+            return true;
+        }
+        if cancelable && me.cancel_requested {
+            return true;
+        }
+        me.state = ThreadState::Blocked;
+    }
+    drop(rt);
+    switch_to_scheduler(current_id);
+    true
+}
+
+/// Anota qué espera el hilo `tid` (o lo borra con `None`).
+pub(crate) fn set_blocked_on(tid: MyThreadId, blocked_on: Option<BlockedOn>) {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    if let Some(t) = table.get_mut(tid) {
+        // los dormidos se indexan por hora de despertar (`wake_sleepers`)
+        if let Some(BlockedOn::Sleep(until)) = t.blocked_on {
+            scheduler::remove_sleeper(tid, until);
+        }
+        t.blocked_on = blocked_on;
+        if let Some(BlockedOn::Sleep(until)) = blocked_on {
+            scheduler::add_sleeper(tid, until);
+        }
+        // los que esperan un mutex o un `join`, para el grafo de espera
+        deadlock::set_waiting(tid, blocked_on);
+    }
+}
+
+/// Despierta (pasa a READY) al hilo `tid` si estaba bloqueado. Si todavía no
+/// se bloqueó, el aviso queda guardado para su próximo `block_current`.
+pub(crate) fn wake_thread(tid: MyThreadId) {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    match table.get(tid).map(|t| t.state) {
+        Some(ThreadState::Blocked) => make_ready(&mut table, tid),
+        Some(ThreadState::Ready | ThreadState::Running) => table[tid].wake_pending = true,
+        _ => {}
+    }
+}
+
+/// Clase y deadline propios del hilo, sin contar préstamos.
+fn base_params(t: &ThreadControlBlock) -> (SchedulerType, Option<u64>) {
+    t.base_sched.unwrap_or((t.scheduler_type, t.deadline_ms))
+}
+
+/// Con qué urgencia se planifica un hilo: clase, deadline absoluto (EDF) y
+/// prioridad fija (RM/DM). Es lo que presta la herencia o el techo de un
+/// `MyMutex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Urgency {
+    pub sched: SchedulerType,
+    pub deadline_ms: Option<u64>,
+    pub fixed_priority: Option<u64>,
+}
+
+impl Urgency {
+    /// Urgencia con que se planifica `t` ahora mismo (con sus préstamos).
+    pub(crate) fn of(t: &ThreadControlBlock) -> Self {
+        Self {
+            sched: t.scheduler_type,
+            deadline_ms: t.deadline_ms,
+            fixed_priority: t.fixed_priority.or_else(|| own_fixed_priority(t)),
+        }
+    }
+
+    /// Clave de `scheduler::priority_key` (menor = más urgente).
+    pub(crate) fn key(&self, tickets: u32, precedence: &[SchedulerType], rt_policy: RtPolicy) -> (usize, u64) {
+        scheduler::priority_key(
+            self.sched,
+            self.deadline_ms,
+            self.fixed_priority,
+            tickets,
+            precedence,
+            rt_policy,
+        )
+    }
+}
+
+/// Prioridad fija propia de `t` con la política RT actual, sin préstamos.
+fn own_fixed_priority(t: &ThreadControlBlock) -> Option<u64> {
+    t.rt_params.map(|p| scheduler::fixed_priority(&p))
+}
+
+/// Cambia la clase/deadline/prioridad fija prestada con que se planifica `t`;
+/// si estaba READY, lo vuelve a encolar (en su nueva clase o con su nueva
+/// clave).
+fn set_sched_params(
+    t: &mut ThreadControlBlock,
+    sched: SchedulerType,
+    deadline_ms: Option<u64>,
+    fixed_priority: Option<u64>,
+) {
+    let old_sched = t.scheduler_type;
+    let changed =
+        (old_sched, t.deadline_ms, t.fixed_priority) != (sched, deadline_ms, fixed_priority);
+    t.scheduler_type = sched;
+    t.deadline_ms = deadline_ms;
+    t.fixed_priority = fixed_priority;
+
+    if changed {
+        requeue(t, old_sched);
+    }
+    index_deadline(t);
+}
+
+/// Si `t` está READY, lo saca de la cola de `old_sched` y lo vuelve a encolar
+/// con sus parámetros actuales.
+fn requeue(t: &mut ThreadControlBlock, old_sched: SchedulerType) {
+    if t.state == ThreadState::Ready {
+        scheduler::notify_dequeue(t.id, old_sched);
+        enqueue(t);
+    }
+}
+
+/// Aplica el más urgente entre los parámetros propios de `t` y sus préstamos.
+fn recompute_boost(t: &mut ThreadControlBlock) {
+    let (sched, deadline_ms) = base_params(t);
+    let base = Urgency { sched, deadline_ms, fixed_priority: own_fixed_priority(t) };
+    let precedence = scheduler::class_precedence();
+    let rt_policy = scheduler::rt_policy();
+    let key = |u: &Urgency| u.key(t.tickets, &precedence, rt_policy);
+
+    let best = t
+        .boosts
+        .iter()
+        .map(|&(_, u)| u)
+        .filter(|u| key(u) < key(&base))
+        .min_by_key(|u| key(u));
+
+    match best {
+        Some(u) => {
+            t.base_sched = Some((base.sched, base.deadline_ms));
+            set_sched_params(t, u.sched, u.deadline_ms, u.fixed_priority);
+        }
+        None => {
+            t.base_sched = None;
+            set_sched_params(t, base.sched, base.deadline_ms, None);
+        }
+    }
+}
+
+/// Presta al hilo `tid` la urgencia `boost` mientras tenga el mutex
+/// `mutex_id` (o la quita con `None`). Si el mutex ya le prestaba algo, solo
+/// se reemplaza cuando `boost` es más urgente o es `None`.
+pub(crate) fn set_boost(tid: MyThreadId, mutex_id: usize, boost: Option<Urgency>) {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return;
+    };
+    let precedence = scheduler::class_precedence();
+    let rt_policy = scheduler::rt_policy();
+    let existing = t.boosts.iter().position(|&(m, _)| m == mutex_id);
+
+    match (boost, existing) {
+        (None, Some(i)) => {
+            t.boosts.remove(i);
+        }
+        (Some(new), Some(i)) => {
+            let old = t.boosts[i].1;
+            let key = |u: &Urgency| u.key(t.tickets, &precedence, rt_policy);
+            if key(&new) < key(&old) {
+                t.boosts[i] = (mutex_id, new);
+            }
+        }
+        (Some(new), None) => t.boosts.push((mutex_id, new)),
+        (None, None) => return,
+    }
+    recompute_boost(t);
+}
+
+/// Urgencia con que se está planificando el hilo `tid` ahora mismo.
+pub(crate) fn effective_params(tid: MyThreadId) -> Option<Urgency> {
+    let rt = runtime::current();
+    let table = rt.threads.lock().unwrap();
+    table.get(tid).map(Urgency::of)
+}
+
+/// Avisa a la política de `t` que quedó READY.
+///
+/// Un hilo de Cfs que llega (creado, despertado o cambiado de clase) no
+/// puede quedar por debajo del menor `vruntime` de la clase.
+fn enqueue(t: &mut ThreadControlBlock) {
+    if t.scheduler_type == SchedulerType::Cfs {
+        t.vruntime = t.vruntime.max(scheduler::cfs_min_vruntime());
+    }
+    scheduler::notify_enqueue(t);
+}
+
+/// Pasa a READY un hilo bloqueado y avisa a su política.
+fn make_ready(table: &mut ThreadTable, tid: MyThreadId) {
+    if let Some(t) = table.get_mut(tid) {
+        if t.state == ThreadState::Blocked {
+            t.state = ThreadState::Ready;
+            enqueue(t);
+        }
+    }
+}
+
+/// Guarda el contexto del hilo `current_id` y vuelve al planificador.
+///
+/// El llamador ya debe haber dejado el estado del hilo (READY, BLOCKED o
+/// FINISHED) y soltado todos los locks.
+fn switch_to_scheduler(current_id: MyThreadId) {
+    let rt = runtime::current();
+    let (from, to) = {
+        let mut table = rt.threads.lock().unwrap();
+        let sched_ctx = rt.scheduler_context.lock().unwrap();
+        (
+            &mut *table[current_id].context as *mut Context,
+            &**sched_ctx as *const Context,
+        )
+    };
+    // el runtime sigue vivo: `run` tiene su propia referencia mientras despacha
+    drop(rt);
+
+    let depth = scheduler::switch_depth();
+    // SAFETY: ver `dispatch`.
+    unsafe { context::switch(from, to) };
+    scheduler::restore_depth(depth);
+}
+
+/// Expropia al hilo que está corriendo: queda READY y el CPU vuelve al
+/// planificador. La llama el manejador de `SIGALRM` (modo expropiativo).
+///
+/// No hace nada si no hay un hilo en CPU, si el hilo ya va camino al
+/// planificador (no está RUNNING) o si otro hilo del SO tiene tomada la tabla.
+pub(crate) fn preempt_current() {
+    if !DISPATCHER_ACTIVE.with(Cell::get) {
+        return;
+    }
+    let Some(rt) = runtime::try_current() else {
+        return;
+    };
+    let current_id = {
+        let Ok(mut table) = rt.threads.try_lock() else {
+            return;
+        };
+        let Ok(current_id) = rt.current.try_lock() else {
+            return;
+        };
+        let Some(current_id) = *current_id else {
+            return;
+        };
+        match table.get_mut(current_id) {
+            Some(t) if t.state == ThreadState::Running => t.state = ThreadState::Ready,
+            _ => return,
+        }
+        current_id
+    };
+    drop(rt);
+
+    switch_to_scheduler(current_id);
+}
+
+/// Punto de entrada de todo hilo nuevo: corre su `start_routine` sobre la
+/// pila propia y al terminar llama a `my_thread_end`.
+extern "C" fn thread_entry() -> ! {
+    // el hilo nuevo no hereda las secciones sin expropiación del despachador
+    scheduler::restore_depth(0);
+    let routine = {
+        let rt = runtime::current();
+        let table = rt.threads.lock().unwrap();
+        let current_id = rt.current.lock().unwrap().expect("hilo sin ID actual");
+        table[current_id].start_routine.clone()
+    };
+
+    if let Some(f) = routine {
+        // un panic no puede cruzar el cambio de contexto: el hilo simplemente termina
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            // cancelado antes de correr por primera vez
+            my_thread_testcancel();
+            f()
+        }));
+        if let Err(payload) = result {
+            if payload.is::<ThreadCanceled>() {
+                run_cleanup_handlers(true);
+            } else if let Ok(exit) = payload.downcast::<ThreadExit>() {
+                set_exit_value(exit.0);
+                run_cleanup_handlers(false);
+            }
+        }
+    }
+
+    my_thread_end();
+    unreachable!("se reanudó un hilo terminado");
+}
+
+/// Pide cancelar el hilo `tid` (como `pthread_cancel`).
+///
+/// La cancelación es diferida: el hilo sigue corriendo hasta su próximo
+/// punto de cancelación (`my_thread_yield_`, `my_thread_join`,
+/// `my_thread_sleep`, `my_thread_testcancel`, y las esperas de `MyMutex`,
+/// `MyCond` y `MySemaphore`). Ahí su pila se desenrolla, corren sus manejadores de
+/// limpieza y el hilo termina; quien le haga `join` recibe `Canceled` en
+/// `MyJoinHandle::join`. Si el hilo está bloqueado en una de esas esperas,
+/// se despierta para cancelarse.
+///
+/// Cancelar un hilo que ya terminó no hace nada.
+pub fn my_thread_cancel(tid: MyThreadId) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    if t.state == ThreadState::Finished {
+        return Ok(());
+    }
+    t.cancel_requested = true;
+    make_ready(&mut table, tid);
+    Ok(())
+}
+
+/// Punto de cancelación explícito (como `pthread_testcancel`): si alguien
+/// pidió cancelar el hilo actual, se cancela aquí.
+///
+/// Útil en ciclos largos que no pasan por otras funciones de la biblioteca.
+pub fn my_thread_testcancel() {
+    if cancel_pending() {
+        panic::resume_unwind(Box::new(ThreadCanceled));
+    }
+}
+
+/// `true` si el hilo actual tiene una cancelación pendiente.
+pub(crate) fn cancel_pending() -> bool {
+    if !DISPATCHER_ACTIVE.with(Cell::get) {
+        return false;
+    }
+    let rt = runtime::current();
+    let Some(current_id) = *rt.current.lock().unwrap() else {
+        return false;
+    };
+    let table = rt.threads.lock().unwrap();
+    table.get(current_id).is_some_and(|t| t.cancel_requested)
+}
+
+/// Registra un manejador de limpieza para el hilo actual (como
+/// `pthread_cleanup_push`).
+///
+/// Si el hilo se cancela o termina con `my_thread_exit`, los manejadores
+/// que sigan registrados corren en orden inverso, todavía en el hilo. Sirven
+/// para soltar lo que el hilo tenga tomado, p. ej. un `MyMutex` que
+/// `MyCond::wait` vuelve a tomar antes de cancelarse.
+///
+/// Fuera de un hilo de la biblioteca no hace nada.
+pub fn my_thread_cleanup_push<F>(handler: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    let rt = runtime::current();
+    let Some(current_id) = *rt.current.lock().unwrap() else {
+        return;
+    };
+    let mut table = rt.threads.lock().unwrap();
+    if let Some(t) = table.get_mut(current_id) {
+        t.cleanup.push(Box::new(handler));
+    }
+}
+
+/// Quita el último manejador de limpieza registrado (como
+/// `pthread_cleanup_pop`); si `execute` es `true`, además lo ejecuta.
+pub fn my_thread_cleanup_pop(execute: bool) {
+    let handler = {
+        let rt = runtime::current();
+        let Some(current_id) = *rt.current.lock().unwrap() else {
+            return;
+        };
+        let mut table = rt.threads.lock().unwrap();
+        table.get_mut(current_id).and_then(|t| t.cleanup.pop())
+    };
+    if let (Some(handler), true) = (handler, execute) {
+        handler();
+    }
+}
+
+/// Corre los manejadores de limpieza pendientes del hilo actual, del último
+/// al primero. Con `canceled`, además deja anotado que el hilo se canceló.
+fn run_cleanup_handlers(canceled: bool) {
+    let rt = runtime::current();
+    let Some(current_id) = *rt.current.lock().unwrap() else {
+        return;
+    };
+    {
+        let mut table = rt.threads.lock().unwrap();
+        let t = &mut table[current_id];
+        // la cancelación ya se atendió: los manejadores no la vuelven a disparar
+        t.cancel_requested = false;
+        t.canceled |= canceled;
+    }
+    drop(rt);
+
+    loop {
+        let handler = {
+            let rt = runtime::current();
+            let mut table = rt.threads.lock().unwrap();
+            table[current_id].cleanup.pop()
+        };
+        let Some(handler) = handler else {
+            break;
+        };
+        // un manejador que entra en pánico no impide correr los demás
+        let _ = panic::catch_unwind(AssertUnwindSafe(handler));
+    }
+}
+
 /// Marca un hilo como "detached", es decir, que no va a ser `join`eado.
-pub fn my_thread_detach(tid: MyThreadId) -> Result<(), &'static str> {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    if tid >= table.len() {
-        return Err("thread does not exist");
+///
+/// Su slot se recicla en cuanto termine (o de una vez, si ya terminó).
+pub fn my_thread_detach(tid: MyThreadId) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    t.detached = true;
+
+    if t.state == ThreadState::Finished {
+        let finished = table.remove(tid);
+        drop(table);
+        drop(finished);
     }
-    table[tid].detached = true;
     Ok(())
 }
 
 /// Cambia el scheduler asignado a un hilo en tiempo de ejecución.
-pub fn my_thread_chsched(tid: MyThreadId, new_sched: SchedulerType) -> Result<(), &'static str> {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    if tid >= table.len() {
-        return Err("thread does not exist");
+///
+/// Pasar a `RealTime` un hilo periódico (`my_thread_set_rt_params`) hace el
+/// test de admisión de la política RT (`scheduler::set_rt_policy`): si con
+/// él el conjunto de tareas RT deja de ser planificable, devuelve
+/// `MyThreadError::NotSchedulable` y el hilo no cambia.
+pub fn my_thread_chsched(tid: MyThreadId, new_sched: SchedulerType) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(params) = table.get(tid).map(|t| t.rt_params) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    if let (SchedulerType::RealTime, Some(params)) = (new_sched, params) {
+        admit(&table, tid, params)?;
     }
-    table[tid].scheduler_type = new_sched;
+    let t = &mut table[tid];
+    let deadline = base_params(t).1;
+    t.base_sched = None;
+    set_sched_params(t, new_sched, deadline, None);
+    // si tenía préstamos vigentes, siguen aplicando sobre la nueva clase
+    recompute_boost(t);
     Ok(())
 }
 
-// Acceso controlado a la tabla global de hilos (`THREAD_TABLE`).
-// Estas funciones encapsulan el uso del `Mutexegúrate que existan estos campos (además de los otros que ya ten` que protege la tabla,
-// permitiendo ejecutar un cierre (`closure`) con acceso seguro a los TCB:
+// Acceso controlado a la tabla de hilos del runtime actual (`Runtime.threads`).
+// Estas funciones toman el `Mutex` que protege la tabla de ese runtime y
+// ejecutan un cierre (`closure`) con acceso seguro a los TCB:
 
-/// Otorga **solo lectura** (`&[ThreadControlBlock]`).
+/// Otorga **solo lectura** (`&ThreadTable`).
 pub fn with_threads<F, R>(f: F) -> R
 where
-    F: FnOnce(&Vec<ThreadControlBlock>) -> R,
+    F: FnOnce(&ThreadTable) -> R,
 {
-    let table = THREAD_TABLE.lock().unwrap();
-    f(&*table)
+    let rt = runtime::current();
+    let table = rt.threads.lock().unwrap();
+    f(&table)
 }
 
 
@@ -294,65 +1524,243 @@ where
 /// Útil para pruebas o para ajustar metadatos de scheduling (tickets, deadlines, etc.).
 pub fn with_threads_mut<F, R>(f: F) -> R
 where
-    F: FnOnce(&mut Vec<ThreadControlBlock>) -> R,
+    F: FnOnce(&mut ThreadTable) -> R,
 {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    f(&mut *table)
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    f(&mut table)
 }
 
-/// Ajusta la cantidad de tickets para Lottery del hilo `tid`.
-pub fn my_thread_set_tickets(tid: MyThreadId, tickets: u32) -> Result<(), &'static str> {
+/// Ajusta la cantidad de tickets para Lottery y Stride del hilo `tid`.
+pub fn my_thread_set_tickets(tid: MyThreadId, tickets: u32) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
     if tickets == 0 {
-        return Err("tickets must be >= 1");
+        return Err(MyThreadError::InvalidTickets(tid));
     }
-    let mut table = THREAD_TABLE.lock().unwrap();
-    if tid >= table.len() {
-        return Err("thread does not exist");
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    t.tickets = tickets;
+    Ok(())
+}
+
+/// Cambia la moneda de Lottery en que están expresados los tickets del hilo
+/// `tid` (`None` para la moneda base). Una moneda que no se haya creado con
+/// `scheduler::set_currency` vale como la base.
+pub fn my_thread_set_currency(tid: MyThreadId, currency: Option<&str>) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    t.currency = currency.map(str::to_owned);
+    Ok(())
+}
+
+/// Ajusta el `nice` (de -20 a 19) con que Cfs pondera el CPU del hilo `tid`:
+/// cada nivel menos le da ~10% más de CPU frente a los demás.
+pub fn my_thread_set_nice(tid: MyThreadId, nice: i8) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    if !(-20..=19).contains(&nice) {
+        return Err(MyThreadError::InvalidNice(tid));
     }
-    table[tid].tickets = tickets;
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    t.nice = nice;
     Ok(())
 }
 
-/// Ajusta el deadline (en ms desde epoch) para RT del hilo `tid`.
-/// Usa `None` para limpiar/eliminar el deadline.
+/// Elige qué pasa cuando el hilo `tid` no cumple un deadline (por defecto
+/// `DeadlineMissAction::Escalate`).
+///
+/// ```rust
+/// use proyecto1::mypthreads::{
+///     my_thread_create, my_thread_run, my_thread_set_deadline_ms, my_thread_set_miss_action,
+///     DeadlineMissAction, SchedulerType,
+/// };
+/// use proyecto1::scheduler;
+///
+/// // un camión de suministros que llega tarde solo se anota en el registro
+/// let truck = my_thread_create(|| {}, SchedulerType::RealTime).unwrap();
+/// my_thread_set_deadline_ms(truck, Some(0)).unwrap();
+/// my_thread_set_miss_action(truck, DeadlineMissAction::Log).unwrap();
+/// my_thread_run();
+///
+/// let misses = scheduler::deadline_miss_events();
+/// assert_eq!(misses[0].tid, truck);
+/// assert!(!scheduler::plant_exploded());
+/// ```
+pub fn my_thread_set_miss_action(tid: MyThreadId, action: DeadlineMissAction) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    t.miss_action = action;
+    Ok(())
+}
+
+/// Ajusta el deadline absoluto para RT del hilo `tid`, en ms del reloj del
+/// planificador de este runtime (`scheduler::now_ms`, real o simulado, que
+/// arranca en 0 al crear el runtime). Usa `None` para limpiar/eliminar el
+/// deadline.
 pub fn my_thread_set_deadline_ms(
     tid: MyThreadId,
     deadline_ms: Option<u64>,
-) -> Result<(), &'static str> {
-    let mut table = THREAD_TABLE.lock().unwrap();
-    if tid >= table.len() {
-        return Err("thread does not exist");
-    }
-    table[tid].deadline_ms = deadline_ms;
+) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get_mut(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
+    };
+    set_own_deadline(t, deadline_ms);
     Ok(())
 }
 
-/// Ejecuta la función asociada al hilo `tid`, si existe.
+/// Cambia el deadline propio de `t`; los préstamos vigentes siguen aplicando.
+fn set_own_deadline(t: &mut ThreadControlBlock, deadline_ms: Option<u64>) {
+    let sched = base_params(t).0;
+    t.base_sched = None;
+    set_sched_params(t, sched, deadline_ms, None);
+    recompute_boost(t);
+}
+
+/// Test de admisión de la política RT actual: ¿sigue siendo planificable el
+/// conjunto de hilos RT periódicos si `tid` pasa a tener los parámetros `params`?
+fn admit(table: &ThreadTable, tid: MyThreadId, params: RtParams) -> Result<(), MyThreadError> {
+    let mut tasks: Vec<RtParams> = table
+        .iter()
+        .filter(|t| t.id != tid && t.state != ThreadState::Finished)
+        .filter(|t| base_params(t).0 == SchedulerType::RealTime)
+        .filter_map(|t| t.rt_params)
+        .collect();
+    tasks.push(params);
+    if scheduler::rt_schedulable(&tasks) {
+        Ok(())
+    } else {
+        Err(MyThreadError::NotSchedulable(tid))
+    }
+}
+
+/// Convierte al hilo `tid` en una tarea periódica (o lo deja de ser, con `None`).
 ///
-/// Por ahora esto ejecuta la función de forma síncrona (sin cambio de contexto real),
-/// lo cual es suficiente para una simulación cooperativa básica.
-pub fn my_thread_run_once(tid: MyThreadId) {
-    let maybe_func = {
-        let table = THREAD_TABLE.lock().unwrap();
-        if let Some(tcb) = table.get(tid) {
-            tcb.start_routine.clone()
-        } else {
-            None
-        }
+/// El primer trabajo se libera ahora: su deadline queda en
+/// `now + relative_deadline_ms`. Si el hilo ya es `RealTime`, primero se hace
+/// el test de admisión (ver `my_thread_chsched`).
+///
+/// ```rust
+/// use proyecto1::mypthreads::*;
+///
+/// let rt = Runtime::new();
+/// rt.enter(|| {
+///     let truck = my_thread_create(|| {
+///         for _ in 0..3 {
+///             // ... un viaje de abastecimiento ...
+///             my_thread_wait_next_period().unwrap();
+///         }
+///     }, SchedulerType::RealTime).unwrap();
+///     my_thread_set_rt_params(truck, Some(RtParams::implicit(20, 5))).unwrap();
+///
+///     // 5/20 + 16/20 > 1: EDF no puede garantizar ambos deadlines
+///     let ambulance = my_thread_create(|| {}, SchedulerType::RealTime).unwrap();
+///     assert_eq!(
+///         my_thread_set_rt_params(ambulance, Some(RtParams::implicit(20, 16))),
+///         Err(MyThreadError::NotSchedulable(ambulance)),
+///     );
+///     my_thread_run();
+/// });
+/// ```
+pub fn my_thread_set_rt_params(tid: MyThreadId, params: Option<RtParams>) -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let mut table = rt.threads.lock().unwrap();
+    let Some(t) = table.get(tid) else {
+        return Err(MyThreadError::NoSuchThread(tid));
     };
+    let Some(params) = params else {
+        let t = &mut table[tid];
+        t.rt_params = None;
+        t.release_ms = None;
+        requeue(t, t.scheduler_type);
+        return Ok(());
+    };
+    if !params.is_valid() {
+        return Err(MyThreadError::InvalidRtParams(tid));
+    }
+    if base_params(t).0 == SchedulerType::RealTime {
+        admit(&table, tid, params)?;
+    }
+
+    let now = scheduler::now_ms();
+    let t = &mut table[tid];
+    t.rt_params = Some(params);
+    t.release_ms = Some(now);
+    set_own_deadline(t, Some(now + params.relative_deadline_ms));
+    // las prioridades fijas (RM/DM) salen de los parámetros
+    requeue(t, t.scheduler_type);
+    Ok(())
+}
 
-    if let Some(f) = maybe_func {
-        // Aquí podríamos marcar RUNNING antes, FINISHED después, etc.
-        f();
+/// Termina el trabajo actual del hilo periódico que llama y lo duerme hasta
+/// la liberación del siguiente, que trae un deadline nuevo.
+///
+/// Si el trabajo terminó después de su deadline se cuenta como incumplido
+/// (`scheduler::deadline_misses`) y se aplica la `miss_action` del hilo; si
+/// se pasó de su período, el siguiente se libera de inmediato. Es un punto de
+/// cancelación.
+pub fn my_thread_wait_next_period() -> Result<(), MyThreadError> {
+    let rt = runtime::current();
+    let current_id = (*rt.current.lock().unwrap()).ok_or(MyThreadError::NoCurrentThread)?;
+    let (next_release, miss) = {
+        let mut table = rt.threads.lock().unwrap();
+        let t = &mut table[current_id];
+        let (Some(params), Some(release)) = (t.rt_params, t.release_ms) else {
+            return Err(MyThreadError::NotPeriodic(current_id));
+        };
+        let now = scheduler::now_ms();
+        // con `Abort`, la espera de abajo es el punto de cancelación
+        let miss = base_params(t)
+            .1
+            .filter(|&deadline| now > deadline)
+            .and_then(|deadline| note_deadline_miss(t, deadline, now));
+        // el deadline del siguiente trabajo ya vale al despertar, para que
+        // compita con él desde su liberación
+        let next_release = release + params.period_ms;
+        t.release_ms = Some(next_release);
+        set_own_deadline(t, Some(next_release + params.relative_deadline_ms));
+        (next_release, miss)
+    };
+    drop(rt);
+    if let Some(miss) = miss {
+        scheduler::log_deadline_miss(&miss);
     }
+
+    my_thread_sleep_until(next_release);
+    Ok(())
+}
+
+/// Cambia el máximo de hilos que puede haber en la tabla a la vez.
+///
+/// Cuentan los hilos vivos y los terminados que nadie ha recogido (`join`);
+/// los slots reciclados no. Si el nuevo máximo es menor que los hilos que ya
+/// hay, esos siguen corriendo y `my_thread_create` falla hasta que se liberen.
+pub fn my_thread_set_max_threads(max_threads: usize) {
+    let rt = runtime::current();
+    rt.threads.lock().unwrap().max_threads = max_threads;
 }
 
-     pub fn my_thread_id() -> MyThreadId {
-         CURRENT_THREAD_ID.lock().unwrap().expect("No current thread is running")
-     }
+/// Máximo actual de hilos en la tabla (64 por defecto).
+pub fn my_thread_max_threads() -> usize {
+    let rt = runtime::current();
+    let max_threads = rt.threads.lock().unwrap().max_threads;
+    max_threads
+}
 
-     /// Sets the ID of the currently running thread.
-/// This is used by the scheduler/main loop to update the global state.
-pub fn set_current_thread_id(tid: MyThreadId) {
-    *CURRENT_THREAD_ID.lock().unwrap() = Some(tid);
+/// Devuelve el ID del hilo que está corriendo.
+pub fn my_thread_id() -> MyThreadId {
+    let rt = runtime::current();
+    let current_id = *rt.current.lock().unwrap();
+    current_id.expect("No current thread is running")
 }
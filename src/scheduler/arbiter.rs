@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::mypthreads::{MyThreadId, SchedulerType};
+
+/// Avance de pass de una clase de peso 1 en el reparto ponderado.
+const CLASS_STRIDE1: u64 = 1 << 20;
+
+/// Cómo se reparte el CPU entre clases (`scheduler::set_class_arbitration`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ClassArbitration {
+    /// Gana siempre la primera clase de la precedencia que tenga hilos READY.
+    /// Una clase alta muy ocupada puede dejar sin CPU a las de abajo.
+    #[default]
+    Strict,
+    /// Las clases con hilos READY se reparten los despachos en proporción a
+    /// su peso (las que no aparecen pesan 1), sin azar: como Stride, pero
+    /// entre clases. A igualdad, decide la precedencia.
+    Weighted(Vec<(SchedulerType, u32)>),
+    /// Como `Strict`, pero si un hilo READY de una clase más baja lleva
+    /// `max_wait_ms` o más esperando, se despacha antes que los demás.
+    Aging { max_wait_ms: u64 },
+}
+
+/// Un hilo que pasó demasiado tiempo READY sin recibir el CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StarvationIncident {
+    pub tid: MyThreadId,
+    pub class: SchedulerType,
+    /// ms que llevaba esperando cuando se detectó.
+    pub waited_ms: u64,
+    /// Hora del reloj del planificador en que se detectó.
+    pub at_ms: u64,
+    /// `true` si la guarda de `ClassArbitration::Aging` lo despachó.
+    pub rescued: bool,
+}
+
+/// Umbral por defecto para reportar inanición (ms).
+const DEFAULT_STARVATION_MS: u64 = 1000;
+
+/// Arbitraje entre clases y registro de inanición de un runtime.
+#[derive(Debug)]
+pub(super) struct Arbiter {
+    pub mode: ClassArbitration,
+    /// Pass de cada clase en el reparto ponderado.
+    pass: HashMap<SchedulerType, u64>,
+    /// Pass de la última clase elegida.
+    global_pass: u64,
+    /// Un hilo READY que espera más que esto se reporta.
+    pub threshold_ms: u64,
+    pub incidents: Vec<StarvationIncident>,
+    /// Desde cuándo esperaba cada hilo la última vez que se reportó, para
+    /// reportar una sola vez cada espera.
+    reported: HashMap<MyThreadId, u64>,
+}
+
+impl Default for Arbiter {
+    fn default() -> Self {
+        Self {
+            mode: ClassArbitration::default(),
+            pass: HashMap::new(),
+            global_pass: 0,
+            threshold_ms: DEFAULT_STARVATION_MS,
+            incidents: Vec::new(),
+            reported: HashMap::new(),
+        }
+    }
+}
+
+impl Arbiter {
+    /// Revisa al hilo que más espera en `class` (READY desde `since_ms`) y
+    /// reporta la espera si pasó el umbral.
+    pub fn observe(&mut self, tid: MyThreadId, class: SchedulerType, since_ms: u64, now_ms: u64) {
+        let waited_ms = now_ms.saturating_sub(since_ms);
+        if waited_ms >= self.threshold_ms && self.reported.get(&tid) != Some(&since_ms) {
+            self.report(tid, class, since_ms, now_ms, false);
+        }
+    }
+
+    fn report(&mut self, tid: MyThreadId, class: SchedulerType, since_ms: u64, now_ms: u64, rescued: bool) {
+        let waited_ms = now_ms.saturating_sub(since_ms);
+        self.reported.insert(tid, since_ms);
+        self.incidents.push(StarvationIncident { tid, class, waited_ms, at_ms: now_ms, rescued });
+    }
+
+    /// El hilo terminó: ya no hay espera que reportar.
+    pub fn forget(&mut self, tid: MyThreadId) {
+        self.reported.remove(&tid);
+    }
+
+    /// Guarda de `Aging`: el hilo que más espera entre las clases por debajo
+    /// de la primera con hilos READY, si ya pasó `max_wait_ms`.
+    ///
+    /// `oldest(class)` da el hilo que más espera en la clase y desde cuándo.
+    pub fn rescue(
+        &mut self,
+        precedence: &[SchedulerType],
+        oldest: impl Fn(SchedulerType) -> Option<(MyThreadId, u64)>,
+        now_ms: u64,
+    ) -> Option<MyThreadId> {
+        let ClassArbitration::Aging { max_wait_ms } = self.mode else {
+            return None;
+        };
+        let (class, (tid, since_ms)) = precedence
+            .iter()
+            .filter_map(|&class| oldest(class).map(|o| (class, o)))
+            .skip(1) // la clase que ganaría de todos modos
+            .filter(|&(_, (_, since_ms))| now_ms.saturating_sub(since_ms) >= max_wait_ms)
+            .min_by_key(|&(_, (_, since_ms))| since_ms)?;
+
+        self.report(tid, class, since_ms, now_ms, true);
+        Some(tid)
+    }
+
+    /// Orden en que se consultan las clases en esta decisión.
+    pub fn class_order(
+        &mut self,
+        precedence: &[SchedulerType],
+        has_ready: impl Fn(SchedulerType) -> bool,
+    ) -> Vec<SchedulerType> {
+        let ClassArbitration::Weighted(_) = self.mode else {
+            return precedence.to_vec();
+        };
+        let global_pass = self.global_pass;
+        let mut order: Vec<(u64, usize, SchedulerType)> = precedence
+            .iter()
+            .enumerate()
+            .filter(|&(_, &class)| has_ready(class))
+            .map(|(rank, &class)| {
+                // una clase que estuvo sin hilos no acumula turnos
+                let pass = self.pass.entry(class).or_insert(global_pass);
+                *pass = (*pass).max(global_pass);
+                (*pass, rank, class)
+            })
+            .collect();
+        order.sort_unstable_by_key(|&(pass, rank, _)| (pass, rank));
+        order.into_iter().map(|(_, _, class)| class).collect()
+    }
+
+    /// Anota que `class` recibió un despacho (para el reparto ponderado).
+    pub fn charge(&mut self, class: SchedulerType) {
+        let ClassArbitration::Weighted(weights) = &self.mode else {
+            return;
+        };
+        let weight = weights
+            .iter()
+            .find(|&&(c, _)| c == class)
+            .map_or(1, |&(_, w)| w.max(1));
+        let pass = self.pass.entry(class).or_insert(self.global_pass);
+        self.global_pass = *pass;
+        *pass += CLASS_STRIDE1 / weight as u64;
+    }
+}
@@ -0,0 +1,68 @@
+use crate::mypthreads::{MyThreadId, ThreadControlBlock};
+use super::policy::Scheduler;
+use super::queue::{KeyedQueue, Ready};
+
+/// Peso de cada valor de `nice` (-20..=19), el mismo de Linux: cada nivel
+/// de nice cambia ~10% el CPU que recibe el hilo. `nice = 0` pesa 1024.
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291,
+    29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906,
+    3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423,
+    335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45,
+    36, 29, 23, 18, 15,
+];
+
+/// Peso de `nice = 0`.
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// Peso de un valor de nice (se recorta a -20..=19).
+pub(crate) fn nice_weight(nice: i8) -> u64 {
+    NICE_TO_WEIGHT[(nice.clamp(-20, 19) + 20) as usize]
+}
+
+/// Cuánto avanza (en µs) el `vruntime` de un hilo con `nice` que usó el CPU
+/// `ran_ms`. Cada despacho cuenta como al menos 1 ms, la resolución del
+/// reloj; si no, un hilo que cede enseguida nunca avanzaría.
+pub(crate) fn vruntime_delta(ran_ms: u64, nice: i8) -> u64 {
+    ran_ms.max(1) * 1000 * NICE_0_WEIGHT / nice_weight(nice)
+}
+
+/// Completely Fair Scheduler: gana el hilo READY con menor `vruntime`.
+///
+/// El `vruntime` del TCB avanza con el CPU que usa el hilo, más lento cuanto
+/// más peso le da su `nice`, así que a la larga cada hilo recibe CPU en
+/// proporción a su peso. Los candidatos se guardan en un árbol ordenado por
+/// (`vruntime`, ID). Un hilo nuevo o que vuelve de bloquearse arranca en el
+/// menor `vruntime` de la clase, para no acaparar el CPU por lo que no usó.
+#[derive(Debug, Default)]
+pub struct CfsScheduler {
+    /// Hilos READY ordenados por (`vruntime`, ID).
+    tree: KeyedQueue<u64>,
+}
+
+impl CfsScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Scheduler for CfsScheduler {
+    fn name(&self) -> &str {
+        "cfs"
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        self.tree.insert(t.id, t.vruntime);
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        self.tree.remove(tid);
+    }
+
+    fn pick_next(&mut self, _ready: &Ready<'_>) -> Option<MyThreadId> {
+        self.tree.first().map(|(_, tid)| tid)
+    }
+}
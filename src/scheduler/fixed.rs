@@ -0,0 +1,129 @@
+use crate::mypthreads::{MyThreadId, RtParams, ThreadControlBlock};
+use super::policy::Scheduler;
+use super::queue::{KeyedQueue, Ready};
+use super::rt::RtPolicy;
+
+/// Tiempo Real con prioridades fijas: Rate Monotonic (menor período, más
+/// prioridad) o Deadline Monotonic (menor deadline relativo, más prioridad).
+///
+/// La prioridad sale de los `RtParams` del hilo, salvo que un `MyMutex` le
+/// preste otra (`fixed_priority`); a igualdad gana el de menor ID, y los hilos
+/// RT sin ninguna de las dos van al final.
+#[derive(Debug)]
+pub struct FixedPriorityScheduler {
+    policy: RtPolicy,
+    /// Hilos READY por prioridad fija.
+    queue: KeyedQueue<u64>,
+}
+
+impl FixedPriorityScheduler {
+    pub fn rate_monotonic() -> Self {
+        Self { policy: RtPolicy::RateMonotonic, queue: KeyedQueue::default() }
+    }
+
+    pub fn deadline_monotonic() -> Self {
+        Self { policy: RtPolicy::DeadlineMonotonic, queue: KeyedQueue::default() }
+    }
+}
+
+/// Clave de prioridad fija de una tarea (menor = más prioritaria).
+pub(super) fn priority(policy: RtPolicy, params: &RtParams) -> u64 {
+    match policy {
+        RtPolicy::DeadlineMonotonic => params.relative_deadline_ms,
+        _ => params.period_ms,
+    }
+}
+
+impl Scheduler for FixedPriorityScheduler {
+    fn name(&self) -> &str {
+        match self.policy {
+            RtPolicy::DeadlineMonotonic => "deadline-monotonic",
+            _ => "rate-monotonic",
+        }
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        let key = t
+            .fixed_priority
+            .or_else(|| t.rt_params.map(|p| priority(self.policy, &p)))
+            .unwrap_or(u64::MAX);
+        self.queue.insert(t.id, key);
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        self.queue.remove(tid);
+    }
+
+    fn pick_next(&mut self, _ready: &Ready<'_>) -> Option<MyThreadId> {
+        self.queue.first().map(|(_, tid)| tid)
+    }
+}
+
+/// Cota de Liu & Layland para `n` tareas: `n (2^(1/n) - 1)`.
+pub fn liu_layland_bound(n: usize) -> f64 {
+    if n == 0 {
+        return 1.0;
+    }
+    let n = n as f64;
+    n * (2f64.powf(1.0 / n) - 1.0)
+}
+
+/// Test de Liu & Layland para RM: la utilización total no pasa de la cota.
+///
+/// Es suficiente pero no necesario, y solo vale con deadlines implícitos;
+/// `fixed_priority_schedulable` da la respuesta exacta.
+pub fn liu_layland_schedulable(tasks: &[RtParams]) -> bool {
+    let utilization: f64 = tasks.iter().map(RtParams::utilization).sum();
+    utilization <= liu_layland_bound(tasks.len()) + 1e-9
+}
+
+/// Análisis de tiempo de respuesta: peor tiempo de respuesta de cada tarea,
+/// con `tasks` ordenadas de mayor a menor prioridad.
+///
+/// Itera `R = C_i + Σ_{j<i} ⌈R / T_j⌉ C_j` hasta el punto fijo; `None` si la
+/// tarea pasa de su deadline relativo (no es planificable).
+///
+/// ```rust
+/// use proyecto1::mypthreads::RtParams;
+/// use proyecto1::scheduler::response_times;
+///
+/// let tasks = [RtParams::implicit(4, 1), RtParams::implicit(6, 2), RtParams::implicit(12, 3)];
+/// assert_eq!(response_times(&tasks), vec![Some(1), Some(3), Some(10)]);
+/// ```
+pub fn response_times(tasks: &[RtParams]) -> Vec<Option<u64>> {
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let higher = &tasks[..i];
+            let mut response = task.wcet_ms;
+            loop {
+                let interference: u64 = higher
+                    .iter()
+                    .map(|h| response.div_ceil(h.period_ms) * h.wcet_ms)
+                    .sum();
+                let next = task.wcet_ms + interference;
+                if next > task.relative_deadline_ms {
+                    return None;
+                }
+                if next == response {
+                    return Some(response);
+                }
+                response = next;
+            }
+        })
+        .collect()
+}
+
+/// Ordena `tasks` por la prioridad fija de `policy` (RM o DM) y hace el
+/// análisis de tiempo de respuesta: `true` si todas cumplen sus deadlines.
+///
+/// Con `RtPolicy::Edf` usa el test de EDF.
+pub fn fixed_priority_schedulable(tasks: &[RtParams], policy: RtPolicy) -> bool {
+    if policy == RtPolicy::Edf {
+        return super::edf_schedulable(tasks);
+    }
+    let mut ordered = tasks.to_vec();
+    ordered.sort_by_key(|p| priority(policy, p));
+    response_times(&ordered).iter().all(Option::is_some)
+}
@@ -1,22 +1,224 @@
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-/// Lottery: sorteo ponderado por `tickets` (u32).
-pub(super) fn pick(lot_ready: Vec<(usize, u32)>) -> Option<usize> {
-    if lot_ready.is_empty() {
-        return None;
+use crate::mypthreads::deadlock;
+use crate::mypthreads::{MyThreadId, SchedulerType, ThreadControlBlock, ThreadState, ThreadTable};
+use super::policy::Scheduler;
+use super::queue::Ready;
+
+/// Lottery: sorteo ponderado por `tickets` (u32), siguiendo el modelo de
+/// Waldspurger:
+///
+/// - **Monedas** (`scheduler::set_currency`): los tickets de un hilo están
+///   expresados en su moneda, y cada moneda reparte su respaldo (en tickets
+///   base) entre los tickets activos de sus hilos.
+/// - **Transferencias**: un hilo bloqueado en un `MyMutex` o en un `join` le
+///   presta su valor al hilo que espera (siguiendo la cadena si ese también
+///   espera), hasta que lo despierten.
+/// - **Compensación**: un hilo que cede el CPU después de usar solo una
+///   fracción `f` de su quantum (medida con el reloj del runtime) compite con
+///   su valor multiplicado por `1/f` hasta que vuelva a ganar un sorteo.
+///
+/// Los sorteos salen de un generador con semilla. La semilla se imprime en el
+/// primer sorteo para poder repetir una corrida (`scheduler::set_lottery_seed`):
+/// con `ClockMode::Virtual`, la misma semilla produce exactamente la misma
+/// secuencia de decisiones. Con el reloj real no: la compensación depende de
+/// cuánto tardó de verdad cada hilo.
+pub struct LotteryScheduler {
+    rng: Box<dyn RngCore + Send>,
+    /// `None` si el generador lo dio el usuario (`with_rng`).
+    seed: Option<u64>,
+    /// Ya se imprimió la semilla.
+    announced: bool,
+    /// Hilos READY de la clase (los que llegaron por `enqueue`).
+    ready: HashSet<MyThreadId>,
+    /// Valor en tickets base de cada candidato para el próximo sorteo
+    /// (lo calcula `prepare`).
+    funding: HashMap<MyThreadId, u64>,
+    /// ms que usó cada hilo en su última porción de CPU.
+    last_slice: HashMap<MyThreadId, u64>,
+    /// Compensación vigente: (quantum, ms usados) del hilo que cedió antes.
+    compensation: HashMap<MyThreadId, (u64, u64)>,
+}
+
+impl LotteryScheduler {
+    /// Sorteo con una semilla al azar.
+    pub fn new() -> Self {
+        Self::with_seed(thread_rng().gen())
+    }
+
+    /// Sorteo reproducible a partir de `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Box::new(StdRng::seed_from_u64(seed)),
+            seed: Some(seed),
+            announced: false,
+            ready: HashSet::new(),
+            funding: HashMap::new(),
+            last_slice: HashMap::new(),
+            compensation: HashMap::new(),
+        }
+    }
+
+    /// Sorteo con un generador propio (p. ej. uno más rápido o uno de pruebas).
+    pub fn with_rng<R: RngCore + Send + 'static>(rng: R) -> Self {
+        Self {
+            rng: Box::new(rng),
+            seed: None,
+            announced: false,
+            ready: HashSet::new(),
+            funding: HashMap::new(),
+            last_slice: HashMap::new(),
+            compensation: HashMap::new(),
+        }
+    }
+}
+
+impl Default for LotteryScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LotteryScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LotteryScheduler")
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scheduler for LotteryScheduler {
+    fn name(&self) -> &str {
+        "lottery"
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        let tid = t.id;
+        self.ready.insert(tid);
+        // volvió a READY por cederlo (no por despertar): ¿usó todo su quantum?
+        if let Some(ran_ms) = self.last_slice.remove(&tid) {
+            let quantum = super::quantum_ms(SchedulerType::Lottery);
+            if ran_ms < quantum {
+                self.compensation.insert(tid, (quantum, ran_ms.max(1)));
+            }
+        }
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        self.ready.remove(&tid);
+        // ganó un sorteo (o dejó la clase): se le acaba la compensación
+        self.compensation.remove(&tid);
     }
-    let total: u64 = lot_ready.iter().map(|&(_, t)| t as u64).sum();
-    if total == 0 {
-        return None;
+
+    fn on_tick(&mut self, tid: MyThreadId, ran_ms: u64) {
+        self.last_slice.insert(tid, ran_ms);
+    }
+
+    fn on_block(&mut self, tid: MyThreadId) {
+        self.ready.remove(&tid);
+        self.last_slice.remove(&tid);
+        self.compensation.remove(&tid);
+    }
+
+    fn on_exit(&mut self, tid: MyThreadId) {
+        self.ready.remove(&tid);
+        self.last_slice.remove(&tid);
+        self.compensation.remove(&tid);
+    }
+
+    fn prepare(&mut self, table: &ThreadTable) {
+        let currencies = super::with_state(|s| s.currencies.lock().unwrap().clone());
+        let edges: HashMap<MyThreadId, MyThreadId> = deadlock::wait_edges(table)
+            .into_iter()
+            .filter(|(waiter, _)| table.get(*waiter).is_some_and(|w| w.state == ThreadState::Blocked))
+            .map(|(waiter, edge)| (waiter, edge.holder()))
+            .collect();
+        // tienen tickets activos los que compiten y los bloqueados que los
+        // están transfiriendo (sin recorrer la tabla)
+        let active = self.ready.iter().chain(edges.keys()).filter_map(|&tid| table.get(tid));
+
+        // tickets activos emitidos en cada moneda
+        let mut issued: HashMap<&str, u64> = HashMap::new();
+        for t in active {
+            if let Some(c) = t.currency.as_deref().filter(|c| currencies.contains_key(*c)) {
+                *issued.entry(c).or_default() += t.tickets as u64;
+            }
+        }
+
+        // valor de los tickets de `t` en la moneda base
+        let value = |t: &ThreadControlBlock| -> u64 {
+            let funding = t.currency.as_deref().and_then(|c| Some((currencies.get(c)?, issued.get(c)?)));
+            match funding {
+                Some((&funding, &issued)) => t.tickets as u64 * funding as u64 / issued.max(1),
+                None => t.tickets as u64,
+            }
+        };
+
+        self.funding.clear();
+        for t in self.ready.iter().filter_map(|&tid| table.get(tid)) {
+            let own = match self.compensation.get(&t.id) {
+                Some(&(quantum, ran_ms)) => value(t) * quantum / ran_ms,
+                None => value(t),
+            };
+            self.funding.insert(t.id, own);
+        }
+
+        // cada hilo bloqueado le presta su valor al final de su cadena de espera
+        for (&waiter, &first) in &edges {
+            let mut holder = first;
+            let mut seen = HashSet::from([waiter]);
+            let mut cycle = false;
+            while let Some(&next) = edges.get(&holder) {
+                if !seen.insert(holder) {
+                    cycle = true;
+                    break;
+                }
+                holder = next;
+            }
+            if cycle {
+                continue;
+            }
+            if let Some(funding) = self.funding.get_mut(&holder) {
+                *funding += value(&table[waiter]);
+            }
+        }
     }
 
-    let pick = thread_rng().gen_range(0..total);
-    let mut acc: u64 = 0;
-    for (idx, tickets) in lot_ready {
-        acc += tickets as u64;
-        if pick < acc {
-            return Some(idx);
+    fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId> {
+        // un hilo sin valor igual participa con 1
+        let funding = &self.funding;
+        let tickets = |t: &ThreadControlBlock| {
+            funding.get(&t.id).copied().unwrap_or(t.tickets as u64).max(1)
+        };
+
+        let total: u64 = ready.iter().map(&tickets).sum();
+        if total == 0 {
+            return None;
+        }
+
+        if !self.announced {
+            self.announced = true;
+            match self.seed {
+                Some(seed) => eprintln!("🎲 lottery: semilla {seed}"),
+                None => eprintln!("🎲 lottery: generador sin semilla conocida"),
+            }
+        }
+
+        let pick = self.rng.gen_range(0..total);
+        let mut acc: u64 = 0;
+        for t in ready.iter() {
+            acc += tickets(t);
+            if pick < acc {
+                return Some(t.id);
+            }
         }
+        None
     }
-    None
 }
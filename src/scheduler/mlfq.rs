@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::mypthreads::{MyThreadId, SchedulerType, ThreadControlBlock};
+use super::policy::Scheduler;
+use super::queue::{Ready, RunQueue};
+
+/// Niveles por defecto.
+const DEFAULT_LEVELS: usize = 3;
+/// Cada cuánto (ms) todos los hilos vuelven al nivel más alto.
+const DEFAULT_BOOST_MS: u64 = 1000;
+
+/// Multi-Level Feedback Queue.
+///
+/// - Los hilos empiezan en el nivel 0 (el más prioritario) y siempre gana el
+///   primero de la cola del nivel más alto que tenga hilos READY.
+/// - Cada nivel da una asignación de CPU de `quantum * 2^nivel` (el quantum
+///   de `SchedulerType::Mlfq`); un hilo que la gasta, aunque sea en varias
+///   porciones, baja un nivel.
+/// - Cada `boost_ms` todos vuelven al nivel 0, para que los hilos largos no
+///   se mueran de hambre.
+/// - Bloquearse (mutex, `join`, `sleep`) no cambia el nivel ni lo gastado.
+#[derive(Debug)]
+pub struct MlfqScheduler {
+    /// Una cola FIFO de hilos READY por nivel.
+    queues: Vec<RunQueue>,
+    /// Nivel de cada hilo de la clase.
+    level: HashMap<MyThreadId, usize>,
+    /// CPU (ms) que lleva gastado cada hilo en su nivel actual.
+    used: HashMap<MyThreadId, u64>,
+    boost_ms: u64,
+    last_boost_ms: u64,
+}
+
+impl MlfqScheduler {
+    pub fn new() -> Self {
+        Self::with_levels(DEFAULT_LEVELS, DEFAULT_BOOST_MS)
+    }
+
+    /// MLFQ con `levels` niveles (mínimo 1) y subida general cada `boost_ms`.
+    pub fn with_levels(levels: usize, boost_ms: u64) -> Self {
+        Self {
+            queues: (0..levels.max(1)).map(|_| RunQueue::default()).collect(),
+            level: HashMap::new(),
+            used: HashMap::new(),
+            boost_ms,
+            last_boost_ms: 0,
+        }
+    }
+
+    /// Nivel en que está el hilo `tid` (0 si todavía no pasó por la clase).
+    pub fn level_of(&self, tid: MyThreadId) -> usize {
+        self.level.get(&tid).copied().unwrap_or(0)
+    }
+
+    /// Asignación de CPU (ms) de un nivel.
+    fn allotment(&self, level: usize) -> u64 {
+        super::quantum_ms(SchedulerType::Mlfq) << level
+    }
+
+    /// Sube todos los hilos al nivel 0, respetando el orden de las colas.
+    fn boost(&mut self) {
+        let mut top = RunQueue::default();
+        for queue in &mut self.queues {
+            for tid in queue.iter() {
+                // el orden de llegada se conserva; la hora no se usa aquí
+                top.push(tid, 0);
+            }
+            *queue = RunQueue::default();
+        }
+        self.queues[0] = top;
+        self.level.values_mut().for_each(|l| *l = 0);
+        self.used.clear();
+    }
+}
+
+impl Default for MlfqScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn name(&self) -> &str {
+        "mlfq"
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        let tid = t.id;
+        let level = *self.level.entry(tid).or_insert(0);
+        self.queues[level].push(tid, 0);
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        let level = self.level_of(tid);
+        self.queues[level].remove(tid);
+    }
+
+    fn on_tick(&mut self, tid: MyThreadId, ran_ms: u64) {
+        let level = self.level_of(tid);
+        let used = self.used.entry(tid).or_default();
+        *used += ran_ms;
+        if *used >= self.allotment(level) && level + 1 < self.queues.len() {
+            self.used.remove(&tid);
+            self.level.insert(tid, level + 1);
+        }
+    }
+
+    fn on_exit(&mut self, tid: MyThreadId) {
+        self.level.remove(&tid);
+        self.used.remove(&tid);
+    }
+
+    fn pick_next(&mut self, _ready: &Ready<'_>) -> Option<MyThreadId> {
+        let now = super::now_ms();
+        if now.saturating_sub(self.last_boost_ms) >= self.boost_ms {
+            self.last_boost_ms = now;
+            self.boost();
+        }
+        self.queues.iter().find_map(RunQueue::first)
+    }
+}
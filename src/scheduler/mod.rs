@@ -1,91 +1,700 @@
-use crate::mypthreads::{with_threads, MyThreadId, SchedulerType, ThreadState};
+use rand::RngCore;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::mypthreads::runtime;
+use crate::mypthreads::thread::{sweep_deadlines, wake_sleepers};
+use crate::mypthreads::{with_threads, DeadlineMissAction, MyThreadId, RtParams, SchedulerType, ThreadControlBlock, ThreadState};
 
 mod util;
 mod rt;
+mod fixed;
 mod lottery;
+mod stride;
+mod mlfq;
+mod cfs;
 mod rr;
+mod preempt;
+mod policy;
+mod queue;
+mod arbiter;
+
+pub use policy::Scheduler;
+pub use queue::Ready;
+pub use arbiter::{ClassArbitration, StarvationIncident};
+pub use rt::{edf_density, edf_schedulable, DeadlineMiss, RtPolicy, RtScheduler};
+pub use fixed::{
+    fixed_priority_schedulable, liu_layland_bound, liu_layland_schedulable, response_times,
+    FixedPriorityScheduler,
+};
+pub use lottery::LotteryScheduler;
+pub use stride::StrideScheduler;
+pub use mlfq::MlfqScheduler;
+pub use cfs::CfsScheduler;
+pub use rr::RrScheduler;
+pub use util::ClockMode;
+pub(crate) use preempt::{lock_state, restore_depth, switch_depth, NoPreempt, Timer};
+
+/// Estado de planificación de un runtime (`mypthreads::Runtime`): colas,
+/// reloj, explosión, modo expropiativo y políticas registradas.
+///
+/// Las funciones públicas de este módulo trabajan sobre el runtime actual.
+pub(crate) struct SchedulerState {
+    /// Hilos READY de cada clase, en orden de llegada. Se mantienen en cada
+    /// transición de estado; `scheduler_next` elige solo entre ellos.
+    run_queues: Mutex<HashMap<SchedulerType, queue::RunQueue>>,
+    /// Hilos dormidos (`my_thread_sleep`), por hora de despertar.
+    sleepers: Mutex<BTreeSet<(u64, MyThreadId)>>,
+    /// Deadlines propios de los hilos `RealTime` sin terminar que todavía no
+    /// se reportaron como incumplidos, por hora.
+    deadlines: Mutex<BTreeSet<(u64, MyThreadId)>>,
+    /// Inicio del reloj real del runtime.
+    start: Instant,
+    clock: Mutex<util::Clock>,
+    exploded: AtomicBool,
+    preempt: preempt::Preemption,
+    registry: Mutex<policy::Registry>,
+    /// Monedas de Lottery: nombre → tickets de la moneda base que la respaldan.
+    currencies: Mutex<HashMap<String, u32>>,
+    /// Política de la clase `RealTime` (decide también el test de admisión).
+    rt_policy: Mutex<RtPolicy>,
+    /// Deadlines incumplidos.
+    deadline_misses: AtomicU64,
+    /// Detalle de cada deadline incumplido, en orden.
+    miss_events: Mutex<Vec<DeadlineMiss>>,
+    /// Menor `vruntime` de la clase `Cfs` (no retrocede); ahí arrancan los
+    /// hilos nuevos o que vuelven de bloquearse.
+    cfs_min_vruntime: AtomicU64,
+    /// Reparto del CPU entre clases y registro de inanición.
+    arbiter: Mutex<arbiter::Arbiter>,
+}
+
+impl SchedulerState {
+    pub(crate) fn new() -> Self {
+        Self {
+            run_queues: Mutex::new(HashMap::new()),
+            sleepers: Mutex::new(BTreeSet::new()),
+            deadlines: Mutex::new(BTreeSet::new()),
+            start: Instant::now(),
+            clock: Mutex::new(util::Clock::default()),
+            exploded: AtomicBool::new(false),
+            preempt: preempt::Preemption::default(),
+            registry: Mutex::new(policy::Registry::with_defaults()),
+            currencies: Mutex::new(HashMap::new()),
+            rt_policy: Mutex::new(RtPolicy::default()),
+            deadline_misses: AtomicU64::new(0),
+            miss_events: Mutex::new(Vec::new()),
+            cfs_min_vruntime: AtomicU64::new(0),
+            arbiter: Mutex::new(arbiter::Arbiter::default()),
+        }
+    }
+}
 
-/// Registrar un hilo en la cola.
-/// Push al RUN_QUEUE.
-pub fn scheduler_add(tid: usize) {
-    util::push_run_queue(tid);
+/// Ejecuta `f` con el estado de planificación del runtime actual.
+fn with_state<R>(f: impl FnOnce(&SchedulerState) -> R) -> R {
+    f(&runtime::current().scheduler)
 }
 
-/// Permite consultar desde fuera si ya "explotó" la planta.
+/// Pone al hilo `tid` en la cola de su clase, si está READY y no estaba ya.
+///
+/// La biblioteca mantiene las colas sola en cada transición de estado; esto
+/// solo hace falta si se cambió el estado de un TCB a mano (`with_threads_mut`).
+pub fn scheduler_add(tid: MyThreadId) {
+    with_threads(|table| {
+        if let Some(t) = table.get(tid).filter(|t| t.state == ThreadState::Ready) {
+            notify_enqueue(t);
+        }
+    });
+}
+
+/// Saca al hilo `tid` de la cola en que esté, sin cambiar su estado.
+pub fn scheduler_remove(tid: MyThreadId) {
+    let classes: Vec<SchedulerType> = with_state(|s| {
+        s.run_queues
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, queue)| queue.contains(tid))
+            .map(|(&class, _)| class)
+            .collect()
+    });
+    for class in classes {
+        notify_dequeue(tid, class);
+    }
+}
+
+/// Permite consultar desde fuera si ya "explotó" la planta: algún hilo con
+/// `DeadlineMissAction::Escalate` no cumplió su deadline.
 pub fn plant_exploded() -> bool {
-    util::exploded()
+    with_state(util::exploded)
+}
+
+/// Vuelve a dejar la planta sin explotar; la simulación puede seguir.
+pub fn reset_explosion() {
+    with_state(util::reset_explosion)
 }
 
 /// Obtener el tiempo actual en milisegundos
 /// CORREGIDO: Envoltorio público para now_ms
+///
+/// Cada runtime tiene su propio reloj, que arranca en 0 al crearlo. Puede
+/// ser real o simulado (`set_clock_mode`).
 pub fn now_ms() -> u64 {
-    util::now_ms()
-}
-
-/// Revisa si algún hilo RT READY ya venció su deadline y marca "explosión".
-fn sweep_deadlines_and_flag() {
-    let now = util::now_ms();
-    // Sólo marcamos el flag; no cambiamos estados.
-    let miss = with_threads(|table| {
-        table.iter().any(|t|
-            t.state != ThreadState::Finished
-            && t.scheduler_type == SchedulerType::RealTime
-            && t.deadline_ms.is_some()
-            && t.deadline_ms.unwrap() <= now
-        )
+    with_state(util::now_ms)
+}
+
+/// Elige la fuente de tiempo del runtime actual.
+///
+/// Conviene elegirla antes de crear hilos: al pasar a `ClockMode::Virtual` el
+/// reloj simulado arranca en la hora actual, pero al volver a `Real` el
+/// tiempo puede saltar.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_thread_run, my_thread_sleep, my_thread_spawn, Runtime, SchedulerType};
+/// use proyecto1::scheduler::{self, ClockMode};
+///
+/// let rt = Runtime::new();
+/// rt.enter(|| {
+///     scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+///     let started = std::time::Instant::now();
+///
+///     let boat = my_thread_spawn(|| {
+///         my_thread_sleep(10_000); // diez segundos simulados
+///         scheduler::now_ms()
+///     }, SchedulerType::RoundRobin).unwrap();
+///     my_thread_run();
+///
+///     assert!(boat.join().unwrap() >= 10_000);
+///     assert!(started.elapsed().as_secs() < 1);
+/// });
+/// ```
+pub fn set_clock_mode(mode: ClockMode) {
+    with_state(|s| util::set_clock_mode(s, mode));
+}
+
+/// Fuente de tiempo actual del runtime.
+pub fn clock_mode() -> ClockMode {
+    with_state(util::clock_mode)
+}
+
+/// Adelanta `ms` el reloj simulado, p. ej. para modelar trabajo que no pasa
+/// por la biblioteca. Con el reloj real no hace nada.
+pub fn advance_clock(ms: u64) {
+    with_state(|s| util::advance(s, ms));
+}
+
+/// Un tick de trabajo simulado (en cada despacho y punto de expropiación).
+pub(crate) fn clock_tick() {
+    with_state(util::tick);
+}
+
+/// Espera hasta el instante `until_ms`; con el reloj simulado, salta hasta él.
+pub(crate) fn wait_until(until_ms: u64) {
+    with_state(|s| util::wait_until(s, until_ms));
+}
+
+/// Activa o desactiva el modo expropiativo.
+///
+/// Con el modo activo y el reloj real, un temporizador interrumpe con
+/// `SIGALRM` al hilo que agota el quantum de su scheduler y el CPU pasa al
+/// siguiente, aunque el hilo esté en un ciclo que no llama a la biblioteca.
+/// La señal solo cambia de contexto en un punto seguro: no dentro de la
+/// biblioteca, de libc (`malloc`, `write`, ...) ni de un panic; si no, se
+/// repite al milisegundo siguiente. Los puntos de expropiación
+/// (`my_thread_preempt_point`, `MyMutex::unlock`) ceden también al agotar el
+/// quantum, y son los únicos con `ClockMode::Virtual`.
+///
+/// Un hilo expropiado puede quedar a medias en cualquier parte de su código:
+/// lo que compartan varios hilos de la biblioteca se protege con `MyMutex`,
+/// no con `std::sync::Mutex` (el otro hilo se quedaría esperando en el mismo
+/// hilo del SO para siempre).
+pub fn set_preemption(enabled: bool) {
+    with_state(|s| preempt::set_enabled(s, enabled));
+}
+
+/// Indica si el modo expropiativo está activo.
+pub fn preemption_enabled() -> bool {
+    with_state(preempt::enabled)
+}
+
+/// Configura el quantum (en ms, mínimo 1) de los hilos de tipo `sched`.
+pub fn set_quantum_ms(sched: SchedulerType, ms: u64) {
+    with_state(|s| preempt::set_quantum(s, sched, ms));
+}
+
+/// Quantum (en ms) que reciben los hilos de tipo `sched`.
+pub fn quantum_ms(sched: SchedulerType) -> u64 {
+    with_state(|s| preempt::quantum(s, sched))
+}
+
+/// Registra (o reemplaza) la política que planifica los hilos de tipo `sched`.
+///
+/// Si la clase todavía no está en el orden de precedencia, se agrega al final,
+/// es decir, con la menor prioridad. Los hilos que ya estaban READY en la
+/// clase se le pasan a la política nueva con `enqueue`.
+///
+/// ```rust
+/// use proyecto1::mypthreads::{MyThreadId, SchedulerType};
+/// use proyecto1::scheduler::{self, Ready, Scheduler};
+///
+/// /// Siempre elige el hilo con menor ID.
+/// struct LowestId;
+///
+/// impl Scheduler for LowestId {
+///     fn name(&self) -> &str { "lowest-id" }
+///     fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId> {
+///         ready.iter().map(|t| t.id).min()
+///     }
+/// }
+///
+/// scheduler::register_policy(SchedulerType::Custom(0), Box::new(LowestId));
+/// assert_eq!(scheduler::class_precedence().last(), Some(&SchedulerType::Custom(0)));
+/// ```
+pub fn register_policy(sched: SchedulerType, mut policy: Box<dyn Scheduler>) {
+    let rt = runtime::current();
+    let table = rt.threads.lock().unwrap();
+    let s = &rt.scheduler;
+    let mut registry = s.registry.lock().unwrap();
+    {
+        let queues = s.run_queues.lock().unwrap();
+        if let Some(queue) = queues.get(&sched) {
+            Ready::new(queue, &table).iter().for_each(|t| policy.enqueue(t));
+        }
+    }
+    registry.policies.insert(sched, policy);
+    if !registry.precedence.contains(&sched) {
+        registry.precedence.push(sched);
+    }
+}
+
+/// Hace reproducibles los sorteos de Lottery en el runtime actual: con
+/// `ClockMode::Virtual`, la misma semilla da exactamente las mismas
+/// decisiones (con el reloj real, la compensación de quien cede el CPU
+/// depende del tiempo de pared; ver `LotteryScheduler`).
+///
+/// Reemplaza la política de `SchedulerType::Lottery` por un
+/// `LotteryScheduler::with_seed(seed)`.
+pub fn set_lottery_seed(seed: u64) {
+    register_policy(SchedulerType::Lottery, Box::new(LotteryScheduler::with_seed(seed)));
+}
+
+/// Como `set_lottery_seed`, pero con un generador propio.
+pub fn set_lottery_rng<R: RngCore + Send + 'static>(rng: R) {
+    register_policy(SchedulerType::Lottery, Box::new(LotteryScheduler::with_rng(rng)));
+}
+
+/// Semilla de la política de `sched`, si usa un generador con semilla conocida
+/// (p. ej. `policy_seed(SchedulerType::Lottery)` para repetir una corrida).
+pub fn policy_seed(sched: SchedulerType) -> Option<u64> {
+    with_state(|s| s.registry.lock().unwrap().policy(sched).and_then(|p| p.seed()))
+}
+
+/// Crea (o cambia) una moneda de Lottery respaldada por `funding` tickets de
+/// la moneda base.
+///
+/// Los hilos con esa moneda (`my_thread_set_currency`) se reparten el
+/// respaldo en proporción a sus `tickets`, así que agregar hilos a una moneda
+/// no le quita CPU a las demás. Sirve para agrupar vehículos por tipo:
+///
+/// ```rust
+/// use proyecto1::mypthreads::{my_thread_create, my_thread_set_currency, my_thread_set_tickets, Runtime, SchedulerType};
+/// use proyecto1::scheduler;
+///
+/// let rt = Runtime::new();
+/// rt.enter(|| {
+///     // las ambulancias, juntas, ganan tres veces más sorteos que los carros
+///     scheduler::set_currency("ambulancia", 300);
+///     scheduler::set_currency("carro", 100);
+///
+///     for i in 0..6 {
+///         let tid = my_thread_create(|| {}, SchedulerType::Lottery).unwrap();
+///         my_thread_set_currency(tid, Some(if i < 2 { "ambulancia" } else { "carro" })).unwrap();
+///         my_thread_set_tickets(tid, 10).unwrap();
+///     }
+///     assert_eq!(scheduler::currency_funding("carro"), Some(100));
+/// });
+/// rt.run();
+/// ```
+pub fn set_currency(name: &str, funding: u32) {
+    with_state(|s| s.currencies.lock().unwrap().insert(name.to_owned(), funding));
+}
+
+/// Elimina una moneda; sus hilos pasan a valer como la moneda base.
+pub fn remove_currency(name: &str) {
+    with_state(|s| s.currencies.lock().unwrap().remove(name));
+}
+
+/// Respaldo (en tickets base) de la moneda `name`, si existe.
+pub fn currency_funding(name: &str) -> Option<u32> {
+    with_state(|s| s.currencies.lock().unwrap().get(name).copied())
+}
+
+/// Elige cómo se planifica la clase `RealTime` en el runtime actual: EDF (por
+/// defecto), Rate Monotonic o Deadline Monotonic.
+///
+/// Registra la política correspondiente y cambia el test de admisión de
+/// `my_thread_chsched` / `my_thread_set_rt_params`: densidad para EDF,
+/// análisis de tiempo de respuesta para RM y DM. Para comparar, se puede
+/// correr la misma carga con cada política y ver `deadline_misses()`.
+pub fn set_rt_policy(policy: RtPolicy) {
+    let scheduler: Box<dyn Scheduler> = match policy {
+        RtPolicy::Edf => Box::new(RtScheduler::new()),
+        RtPolicy::RateMonotonic => Box::new(FixedPriorityScheduler::rate_monotonic()),
+        RtPolicy::DeadlineMonotonic => Box::new(FixedPriorityScheduler::deadline_monotonic()),
+    };
+    register_policy(SchedulerType::RealTime, scheduler);
+    with_state(|s| *s.rt_policy.lock().unwrap() = policy);
+}
+
+/// Política actual de la clase `RealTime`.
+pub fn rt_policy() -> RtPolicy {
+    with_state(|s| *s.rt_policy.lock().unwrap())
+}
+
+/// Prioridad fija de una tarea con la política RT actual (menor = más
+/// prioritaria): período con RM, deadline relativo con DM. Con EDF se calcula
+/// igual que con RM, pero no se usa para planificar.
+pub(crate) fn fixed_priority(params: &RtParams) -> u64 {
+    fixed::priority(rt_policy(), params)
+}
+
+/// Test de admisión de la política RT actual para el conjunto `tasks`.
+pub fn rt_schedulable(tasks: &[RtParams]) -> bool {
+    fixed_priority_schedulable(tasks, rt_policy())
+}
+
+/// Cantidad de deadlines incumplidos en el runtime actual (ver
+/// `ThreadControlBlock::deadline_misses` por hilo).
+pub fn deadline_misses() -> u64 {
+    with_state(|s| s.deadline_misses.load(Ordering::SeqCst))
+}
+
+/// Deadlines incumplidos del runtime actual, en el orden en que se detectaron.
+pub fn deadline_miss_events() -> Vec<DeadlineMiss> {
+    with_state(|s| s.miss_events.lock().unwrap().clone())
+}
+
+/// Olvida los incumplimientos ya reportados y pone en 0 `deadline_misses()`.
+pub fn clear_deadline_miss_events() {
+    with_state(|s| {
+        s.miss_events.lock().unwrap().clear();
+        s.deadline_misses.store(0, Ordering::SeqCst);
     });
-    if miss { util::mark_explosion(); }
 }
 
-/// Selecciona el próximo hilo a ejecutar (devuelve su ID) con prioridad:
-/// 1) RealTime  -> hilo READY con menor `deadline_ms`
-/// 2) Lottery   -> sorteo ponderado por `tickets`
-/// 3) RoundRobin-> rotación circular
-pub fn scheduler_next() -> Option<MyThreadId> {
-    // Barrido de deadlines antes de decidir
-    sweep_deadlines_and_flag();
-    
-    // 1) Snapshot de candidatos READY bajo lock
-    let (mut rt_ready, lot_ready, rr_ready) = with_threads(|table| {
-        let mut rt: Vec<(usize, u64)> = Vec::new();
-        let mut lot: Vec<(usize, u32)> = Vec::new();
-        let mut rr: Vec<usize> = Vec::new();
-
-        for (idx, t) in table.iter().enumerate() {
-            if t.state != ThreadState::Ready {
-                continue;
+/// Anota un deadline incumplido y aplica la parte de la reacción que toca al
+/// planificador (explotar); cancelar el hilo y avisar (`log_deadline_miss`)
+/// quedan para quien llama.
+pub(crate) fn record_deadline_miss(miss: DeadlineMiss) {
+    with_state(|s| {
+        s.deadline_misses.fetch_add(1, Ordering::SeqCst);
+        s.miss_events.lock().unwrap().push(miss);
+        if miss.action == DeadlineMissAction::Escalate {
+            util::mark_explosion(s);
+        }
+    });
+}
+
+/// Avisa por stderr de un incumplimiento con `DeadlineMissAction::Log`; las
+/// demás reacciones no imprimen. Se llama sin la tabla de hilos tomada.
+pub(crate) fn log_deadline_miss(miss: &DeadlineMiss) {
+    let DeadlineMiss { tid, deadline_ms, lateness_ms, action, .. } = *miss;
+    if action == DeadlineMissAction::Log {
+        eprintln!("⏰ deadline: hilo {tid} no cumplió {deadline_ms} ms ({lateness_ms} ms tarde)");
+    }
+}
+
+/// Define en qué orden se consultan las clases: con `ClassArbitration::Strict`
+/// (por defecto) la primera que tenga un hilo READY gana. Los hilos de una
+/// clase que no aparezca en `order` no se despachan.
+///
+/// Por defecto el orden es `RealTime`, `Lottery`, `Stride`, `Mlfq`, `Cfs`,
+/// `RoundRobin`.
+pub fn set_class_precedence(order: &[SchedulerType]) {
+    with_state(|s| s.registry.lock().unwrap().precedence = order.to_vec());
+}
+
+/// Orden actual de precedencia entre clases.
+pub fn class_precedence() -> Vec<SchedulerType> {
+    with_state(|s| s.registry.lock().unwrap().precedence.clone())
+}
+
+/// Elige cómo se reparte el CPU entre clases en el runtime actual.
+///
+/// ```rust
+/// use proyecto1::mypthreads::SchedulerType;
+/// use proyecto1::scheduler::{self, ClassArbitration};
+///
+/// // RealTime recibe 3 de cada 4 despachos cuando RoundRobin también tiene trabajo
+/// scheduler::set_class_arbitration(ClassArbitration::Weighted(vec![
+///     (SchedulerType::RealTime, 3),
+///     (SchedulerType::RoundRobin, 1),
+/// ]));
+/// assert!(matches!(scheduler::class_arbitration(), ClassArbitration::Weighted(_)));
+///
+/// // o: prioridad estricta, pero nadie espera READY más de 200 ms
+/// scheduler::set_class_arbitration(ClassArbitration::Aging { max_wait_ms: 200 });
+/// ```
+pub fn set_class_arbitration(mode: ClassArbitration) {
+    with_state(|s| s.arbiter.lock().unwrap().mode = mode);
+}
+
+/// Arbitraje actual entre clases.
+pub fn class_arbitration() -> ClassArbitration {
+    with_state(|s| s.arbiter.lock().unwrap().mode.clone())
+}
+
+/// Desde cuántos ms READY sin recibir el CPU se reporta un hilo como
+/// hambriento (por defecto 1000). Cada espera se reporta una sola vez, en
+/// `starvation_incidents` (no se imprime nada).
+pub fn set_starvation_threshold_ms(ms: u64) {
+    with_state(|s| s.arbiter.lock().unwrap().threshold_ms = ms);
+}
+
+/// Umbral actual de inanición (ms).
+pub fn starvation_threshold_ms() -> u64 {
+    with_state(|s| s.arbiter.lock().unwrap().threshold_ms)
+}
+
+/// Incidentes de inanición del runtime actual, en el orden en que se detectaron.
+pub fn starvation_incidents() -> Vec<StarvationIncident> {
+    with_state(|s| s.arbiter.lock().unwrap().incidents.clone())
+}
+
+/// Olvida los incidentes de inanición ya reportados.
+pub fn clear_starvation_incidents() {
+    with_state(|s| s.arbiter.lock().unwrap().incidents.clear());
+}
+
+/// Clave de urgencia de un hilo (menor = más urgente): posición de su clase en
+/// la precedencia y, dentro de ella, lo que ordena esa clase: deadline absoluto
+/// (RT con EDF), prioridad fija (RT con RM o DM) o tickets (Lottery, Stride).
+///
+/// La usan las colas de espera por prioridad y la herencia de prioridad.
+pub(crate) fn priority_key(
+    sched: SchedulerType,
+    deadline_ms: Option<u64>,
+    fixed_priority: Option<u64>,
+    tickets: u32,
+    precedence: &[SchedulerType],
+    rt_policy: RtPolicy,
+) -> (usize, u64) {
+    let rank = precedence
+        .iter()
+        .position(|&c| c == sched)
+        .unwrap_or(precedence.len());
+    let within = match sched {
+        SchedulerType::RealTime => match rt_policy {
+            RtPolicy::Edf => deadline_ms.unwrap_or(u64::MAX),
+            _ => fixed_priority.unwrap_or(u64::MAX),
+        },
+        SchedulerType::Lottery | SchedulerType::Stride => u64::MAX - tickets as u64,
+        _ => 0,
+    };
+    (rank, within)
+}
+
+// ---- Ganchos que llama `mypthreads` en cada transición de estado ----
+
+pub(crate) fn notify_enqueue(t: &ThreadControlBlock) {
+    with_state(|s| {
+        let now = util::now_ms(s);
+        let pushed = s
+            .run_queues
+            .lock()
+            .unwrap()
+            .entry(t.scheduler_type)
+            .or_default()
+            .push(t.id, now);
+        if !pushed {
+            return;
+        }
+        if let Some(p) = s.registry.lock().unwrap().policy(t.scheduler_type) {
+            p.enqueue(t);
+        }
+    });
+}
+
+pub(crate) fn notify_dequeue(tid: MyThreadId, sched: SchedulerType) {
+    with_state(|s| {
+        let removed = s.run_queues.lock().unwrap().get_mut(&sched).is_some_and(|q| q.remove(tid));
+        if !removed {
+            return;
+        }
+        if let Some(p) = s.registry.lock().unwrap().policy(sched) {
+            p.dequeue(tid);
+        }
+    });
+}
+
+pub(crate) fn notify_tick(tid: MyThreadId, sched: SchedulerType, ran_ms: u64) {
+    with_state(|s| {
+        if let Some(p) = s.registry.lock().unwrap().policy(sched) {
+            p.on_tick(tid, ran_ms);
+        }
+    });
+}
+
+pub(crate) fn notify_block(tid: MyThreadId, sched: SchedulerType) {
+    with_state(|s| {
+        if let Some(p) = s.registry.lock().unwrap().policy(sched) {
+            p.on_block(tid);
+        }
+    });
+}
+
+pub(crate) fn notify_exit(tid: MyThreadId, sched: SchedulerType) {
+    with_state(|s| {
+        if let Some(p) = s.registry.lock().unwrap().policy(sched) {
+            p.on_exit(tid);
+        }
+        s.arbiter.lock().unwrap().forget(tid);
+    });
+}
+
+/// Anota que `tid` duerme hasta `until_ms`.
+pub(crate) fn add_sleeper(tid: MyThreadId, until_ms: u64) {
+    with_state(|s| s.sleepers.lock().unwrap().insert((until_ms, tid)));
+}
+
+/// Borra la anotación de `add_sleeper`.
+pub(crate) fn remove_sleeper(tid: MyThreadId, until_ms: u64) {
+    with_state(|s| s.sleepers.lock().unwrap().remove(&(until_ms, tid)));
+}
+
+/// Saca a los dormidos cuya hora ya llegó; devuelve sus IDs y la hora del
+/// próximo de los que siguen durmiendo.
+pub(crate) fn take_due_sleepers(now_ms: u64) -> (Vec<MyThreadId>, Option<u64>) {
+    with_state(|s| {
+        let mut sleepers = s.sleepers.lock().unwrap();
+        let mut due = Vec::new();
+        while let Some(&(until, tid)) = sleepers.first() {
+            if until > now_ms {
+                return (due, Some(until));
             }
-            match t.scheduler_type {
-                SchedulerType::RealTime => {
-                    if let Some(d) = t.deadline_ms {
-                        rt.push((idx, d));
-                    } else {
-                        rr.push(idx);
-                    }
-                }
-                SchedulerType::Lottery => {
-                    let tickets = if t.tickets == 0 { 1 } else { t.tickets };
-                    lot.push((idx, tickets));
-                }
-                SchedulerType::RoundRobin => {
-                    rr.push(idx);
-                }
+            sleepers.pop_first();
+            due.push(tid);
+        }
+        (due, None)
+    })
+}
+
+/// Anota que el deadline propio de `tid` vence en `deadline_ms`.
+pub(crate) fn add_deadline(tid: MyThreadId, deadline_ms: u64) {
+    with_state(|s| s.deadlines.lock().unwrap().insert((deadline_ms, tid)));
+}
+
+/// Borra la anotación de `add_deadline`.
+pub(crate) fn remove_deadline(tid: MyThreadId, deadline_ms: u64) {
+    with_state(|s| s.deadlines.lock().unwrap().remove(&(deadline_ms, tid)));
+}
+
+/// Saca los deadlines que ya vencieron a `now_ms`, del más viejo al más nuevo.
+pub(crate) fn take_due_deadlines(now_ms: u64) -> Vec<(MyThreadId, u64)> {
+    with_state(|s| {
+        let mut deadlines = s.deadlines.lock().unwrap();
+        let mut due = Vec::new();
+        while let Some(&(deadline, tid)) = deadlines.first() {
+            if deadline > now_ms {
+                break;
             }
+            deadlines.pop_first();
+            due.push((tid, deadline));
         }
-        (rt, lot, rr)
+        due
+    })
+}
+
+/// Avance del `vruntime` de un hilo con `nice` que usó el CPU `ran_ms`.
+pub(crate) fn cfs_charge(ran_ms: u64, nice: i8) -> u64 {
+    cfs::vruntime_delta(ran_ms, nice)
+}
+
+/// Se despachó un hilo de `Cfs` con este `vruntime` (el menor de la clase).
+pub(crate) fn cfs_observe(vruntime: u64) {
+    with_state(|s| s.cfs_min_vruntime.fetch_max(vruntime, Ordering::SeqCst));
+}
+
+/// `vruntime` en que arranca un hilo de `Cfs` nuevo o que vuelve de bloquearse.
+pub(crate) fn cfs_min_vruntime() -> u64 {
+    with_state(|s| s.cfs_min_vruntime.load(Ordering::SeqCst))
+}
+
+/// Anota que el hilo despachado empieza a usar el CPU ahora.
+pub(crate) fn start_slice(sched: SchedulerType) {
+    with_state(|s| {
+        util::tick(s);
+        preempt::start_slice(s, sched);
     });
+}
 
-    // 2) Decisión fuera del lock
-    if let Some(tid) = rt::pick(&mut rt_ready) {
-        return Some(tid);
-    }
+/// Cierra la porción del hilo que acaba de soltar el CPU; devuelve los
+/// milisegundos que la usó.
+pub(crate) fn end_slice() -> u64 {
+    with_state(preempt::end_slice)
+}
+
+/// `true` si el modo expropiativo está activo y el hilo actual agotó su quantum.
+pub fn quantum_expired() -> bool {
+    with_state(preempt::expired)
+}
 
-    if let Some(tid) = lottery::pick(lot_ready) {
+/// Selecciona el próximo hilo a ejecutar (devuelve su ID).
+///
+/// Antes reporta los deadlines RT vencidos (`deadline_miss_events`) y
+/// despierta a los hilos de `my_thread_sleep` cuya hora ya llegó.
+///
+/// Recorre las clases en el orden de `class_precedence()` (o el que diga
+/// `class_arbitration()`) y le pide a la política de cada una que elija entre
+/// sus hilos READY (la cola de la clase, sin recorrer la tabla de hilos).
+/// También reporta a los hilos que llevan demasiado READY
+/// (`starvation_incidents`). Por defecto:
+/// 1) RealTime  -> EDF: hilo READY con menor `deadline_ms`
+/// 2) Lottery   -> sorteo ponderado por `tickets` (monedas, transferencias, compensación)
+/// 3) Stride    -> menor *pass* (reparto por `tickets` sin azar)
+/// 4) Mlfq      -> cola del nivel más alto (ver `MlfqScheduler`)
+/// 5) Cfs       -> menor `vruntime` (ver `CfsScheduler`)
+/// 6) RoundRobin-> el primero de la cola (rotación en orden de llegada)
+pub fn scheduler_next() -> Option<MyThreadId> {
+    let now = now_ms();
+    // Barrido de deadlines antes de decidir
+    sweep_deadlines(now);
+    // los hilos dormidos cuya hora ya llegó vuelven a competir
+    wake_sleepers(now);
+
+    let rt = runtime::current();
+    let table = rt.threads.lock().unwrap();
+    let mut registry = rt.scheduler.registry.lock().unwrap();
+    let queues = rt.scheduler.run_queues.lock().unwrap();
+    let mut arbiter = rt.scheduler.arbiter.lock().unwrap();
+    let precedence = registry.precedence.clone();
+    let oldest = |class| queues.get(&class).and_then(queue::RunQueue::oldest);
+
+    // el que más espera en cada clase es el candidato a estar hambriento
+    for &class in &precedence {
+        if let Some((tid, since)) = oldest(class) {
+            arbiter.observe(tid, class, since, now);
+        }
+    }
+    if let Some(tid) = arbiter.rescue(&precedence, oldest, now) {
         return Some(tid);
     }
 
-    rr::pick(rr_ready)
+    let order = arbiter.class_order(&precedence, |class| oldest(class).is_some());
+    for class in order {
+        let Some(queue) = queues.get(&class).filter(|q| !q.is_empty()) else {
+            continue;
+        };
+        let Some(policy) = registry.policy(class) else {
+            continue;
+        };
+        policy.prepare(&table);
+        if let Some(tid) = policy.pick_next(&Ready::new(queue, &table)) {
+            arbiter.charge(class);
+            return Some(tid);
+        }
+    }
+    None
 }
-
-// ELIMINA esta línea - no necesitas re-exportar
-// pub use util::{now_ms, plant_exploded};
\ No newline at end of file
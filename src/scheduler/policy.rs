@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::mypthreads::{MyThreadId, SchedulerType, ThreadControlBlock, ThreadTable};
+use super::queue::Ready;
+use super::{
+    cfs::CfsScheduler, lottery::LotteryScheduler, mlfq::MlfqScheduler, rr::RrScheduler,
+    rt::RtScheduler, stride::StrideScheduler,
+};
+
+/// Política de planificación para **una clase** de hilos (`SchedulerType`).
+///
+/// El planificador general mantiene una política por clase y las consulta
+/// en el orden de precedencia configurado (`set_class_precedence`): la
+/// primera clase que devuelva un hilo gana.
+///
+/// Los ganchos `enqueue`, `dequeue`, `on_tick`, `on_block` y `on_exit` avisan
+/// de las transiciones de los hilos de la clase, así que una política puede
+/// mantener su propia cola ordenada y elegir en O(1) u O(log n). Una política
+/// sin estado puede ignorarlos y decidir solo con los candidatos de `pick_next`.
+///
+/// Todos los métodos se llaman con la tabla de hilos tomada: no deben
+/// usar `with_threads` ni otras funciones de `mypthreads`.
+pub trait Scheduler: Send {
+    /// Nombre corto de la política (para logs).
+    fn name(&self) -> &str;
+
+    /// El hilo `t` quedó READY (creado, cedió el CPU o fue despertado).
+    fn enqueue(&mut self, _t: &ThreadControlBlock) {}
+
+    /// El hilo `tid` dejó de estar READY: fue despachado, terminó o cambió de clase.
+    fn dequeue(&mut self, _tid: MyThreadId) {}
+
+    /// Se llama justo antes de `pick_next` con la tabla completa, para las
+    /// políticas que necesitan ver también a los hilos que no están READY
+    /// (p. ej. Lottery, por las transferencias de tickets).
+    fn prepare(&mut self, _table: &ThreadTable) {}
+
+    /// Elige el siguiente hilo entre los candidatos READY de esta clase (en
+    /// orden de llegada). `ready` nunca viene vacío, y son exactamente los
+    /// hilos que llegaron por `enqueue` y no salieron por `dequeue`.
+    fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId>;
+
+    /// El hilo `tid` soltó el CPU después de usarlo `ran_ms` milisegundos.
+    fn on_tick(&mut self, _tid: MyThreadId, _ran_ms: u64) {}
+
+    /// El hilo `tid` se bloqueó (join, mutex, etc.).
+    fn on_block(&mut self, _tid: MyThreadId) {}
+
+    /// El hilo `tid` terminó: la política puede olvidar lo que guardaba de él.
+    fn on_exit(&mut self, _tid: MyThreadId) {}
+
+    /// Semilla del generador aleatorio de la política, si usa uno con semilla
+    /// conocida. Sirve para repetir una corrida.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Políticas registradas y orden en que se consultan las clases.
+pub(super) struct Registry {
+    pub policies: HashMap<SchedulerType, Box<dyn Scheduler>>,
+    pub precedence: Vec<SchedulerType>,
+}
+
+impl Registry {
+    pub fn with_defaults() -> Self {
+        let mut policies: HashMap<SchedulerType, Box<dyn Scheduler>> = HashMap::new();
+        policies.insert(SchedulerType::RealTime, Box::new(RtScheduler::new()));
+        policies.insert(SchedulerType::Lottery, Box::new(LotteryScheduler::new()));
+        policies.insert(SchedulerType::Stride, Box::new(StrideScheduler::new()));
+        policies.insert(SchedulerType::Mlfq, Box::new(MlfqScheduler::new()));
+        policies.insert(SchedulerType::Cfs, Box::new(CfsScheduler::new()));
+        policies.insert(SchedulerType::RoundRobin, Box::new(RrScheduler::new()));
+        Self {
+            policies,
+            precedence: vec![
+                SchedulerType::RealTime,
+                SchedulerType::Lottery,
+                SchedulerType::Stride,
+                SchedulerType::Mlfq,
+                SchedulerType::Cfs,
+                SchedulerType::RoundRobin,
+            ],
+        }
+    }
+
+    pub fn policy(&mut self, sched: SchedulerType) -> Option<&mut Box<dyn Scheduler>> {
+        self.policies.get_mut(&sched)
+    }
+}
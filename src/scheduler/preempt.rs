@@ -0,0 +1,394 @@
+//! Modo expropiativo.
+//!
+//! Mientras `my_thread_run` despacha, un hilo del SO (`Timer`) vigila la
+//! porción del hilo que está en CPU; cuando se le acaba el quantum le manda
+//! `SIGALRM` al hilo del despachador. El manejador de la señal cambia de
+//! contexto hacia el planificador ahí mismo, así que hasta un ciclo que nunca
+//! llama a la biblioteca se reparte en el tiempo.
+//!
+//! Solo se expropia en un punto seguro: ni dentro de la biblioteca (que marca
+//! con `NoPreempt` las secciones donde tiene locks tomados), ni en código que
+//! no sea del programa (libc: `malloc`, `write`, ...), ni durante un panic. Si
+//! la señal llega en otro punto, se vuelve a intentar en el siguiente aviso,
+//! y los puntos de expropiación cooperativos (`my_thread_preempt_point`,
+//! `unlock`) siguen revisando el quantum.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{compiler_fence, AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::mypthreads::runtime::RuntimeInner;
+use crate::mypthreads::SchedulerType;
+use super::SchedulerState;
+use super::util::{self, now_ms, ClockMode};
+
+/// Quantum por defecto (ms) para cualquier tipo de scheduler sin valor propio.
+pub(super) const DEFAULT_QUANTUM_MS: u64 = 50;
+
+/// Configuración y porción actual del modo expropiativo de un runtime.
+#[derive(Debug, Default)]
+pub(super) struct Preemption {
+    /// Si es `true`, los hilos ceden el CPU al agotar su quantum.
+    enabled: AtomicBool,
+    /// Quantum configurado por tipo de scheduler.
+    quanta: Mutex<HashMap<SchedulerType, u64>>,
+    /// Porción de CPU que está usando el hilo despachado ahora mismo.
+    current_slice: Mutex<Option<Slice>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slice {
+    start_ms: u64,
+    quantum_ms: u64,
+}
+
+/// Cada cuánto (ms) revisa el `Timer` si hay que activarse, mientras el modo
+/// expropiativo está apagado o nadie está en CPU.
+const IDLE_POLL_MS: u64 = 10;
+/// Cada cuánto (ms) se repite el aviso a un hilo que agotó su quantum pero
+/// no estaba en un punto seguro.
+const RETRY_MS: u64 = 1;
+
+thread_local! {
+    /// Secciones `NoPreempt` abiertas por el contexto que corre ahora en este
+    /// hilo del SO. Cada contexto conserva la suya al cambiar (ver
+    /// `switch_depth`).
+    static NO_PREEMPT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Sección en la que el hilo actual no se puede expropiar por señal: la
+/// biblioteca tiene (o puede tener) tomado algún lock, y el planificador o
+/// el próximo hilo lo necesitarían.
+pub(crate) struct NoPreempt(());
+
+impl NoPreempt {
+    pub(crate) fn new() -> Self {
+        NO_PREEMPT.with(|d| d.set(d.get() + 1));
+        compiler_fence(Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for NoPreempt {
+    fn drop(&mut self) {
+        compiler_fence(Ordering::SeqCst);
+        NO_PREEMPT.with(|d| d.set(d.get() - 1));
+    }
+}
+
+/// Lock de un estado interno de la biblioteca (mutex, semáforo, ...) que no
+/// se suelta por una expropiación: otro hilo verde que lo pidiera en el mismo
+/// hilo del SO quedaría bloqueado para siempre.
+pub(crate) struct Locked<'a, T> {
+    guard: MutexGuard<'a, T>,
+    _no_preempt: NoPreempt,
+}
+
+impl<T> std::ops::Deref for Locked<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> std::ops::DerefMut for Locked<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+/// Toma `state` dentro de una sección `NoPreempt`.
+pub(crate) fn lock_state<T>(state: &Mutex<T>) -> Locked<'_, T> {
+    let no_preempt = NoPreempt::new();
+    Locked { guard: state.lock().unwrap(), _no_preempt: no_preempt }
+}
+
+/// Secciones `NoPreempt` abiertas por el contexto actual. Quien cambia de
+/// contexto la guarda antes y la restaura al volver: así cada hilo verde (y
+/// el despachador) tiene la suya aunque compartan hilo del SO.
+pub(crate) fn switch_depth() -> usize {
+    NO_PREEMPT.with(Cell::get)
+}
+
+/// Restaura lo que devolvió `switch_depth` (o arranca en 0 un hilo nuevo).
+pub(crate) fn restore_depth(depth: usize) {
+    NO_PREEMPT.with(|d| d.set(depth));
+}
+
+pub(super) fn set_enabled(s: &SchedulerState, enabled: bool) {
+    s.preempt.enabled.store(enabled, Ordering::SeqCst);
+}
+
+pub(super) fn enabled(s: &SchedulerState) -> bool {
+    s.preempt.enabled.load(Ordering::SeqCst)
+}
+
+pub(super) fn set_quantum(s: &SchedulerState, sched: SchedulerType, ms: u64) {
+    s.preempt.quanta.lock().unwrap().insert(sched, ms.max(1));
+}
+
+pub(super) fn quantum(s: &SchedulerState, sched: SchedulerType) -> u64 {
+    s.preempt
+        .quanta
+        .lock()
+        .unwrap()
+        .get(&sched)
+        .copied()
+        .unwrap_or(DEFAULT_QUANTUM_MS)
+}
+
+// arranca la porción del hilo despachado con el quantum de su clase
+pub(super) fn start_slice(s: &SchedulerState, sched: SchedulerType) {
+    let slice = Slice {
+        start_ms: now_ms(s),
+        quantum_ms: quantum(s, sched),
+    };
+    *s.preempt.current_slice.lock().unwrap() = Some(slice);
+}
+
+// cierra la porción del hilo que soltó el CPU; devuelve los ms que la usó
+pub(super) fn end_slice(s: &SchedulerState) -> u64 {
+    match s.preempt.current_slice.lock().unwrap().take() {
+        Some(slice) => now_ms(s).saturating_sub(slice.start_ms),
+        None => 0,
+    }
+}
+
+// ms que le quedan de quantum al hilo despachado (`None` si no hay ninguno)
+fn remaining(s: &SchedulerState) -> Option<u64> {
+    let slice = (*s.preempt.current_slice.lock().unwrap())?;
+    let elapsed = now_ms(s).saturating_sub(slice.start_ms);
+    Some(slice.quantum_ms.saturating_sub(elapsed))
+}
+
+// ¿el hilo despachado ya gastó todo su quantum?
+pub(super) fn expired(s: &SchedulerState) -> bool {
+    if !enabled(s) {
+        return false;
+    }
+    match *s.preempt.current_slice.lock().unwrap() {
+        Some(slice) => now_ms(s).saturating_sub(slice.start_ms) >= slice.quantum_ms,
+        None => false,
+    }
+}
+
+/// Vigía del quantum de un `my_thread_run`: hilo del SO que le manda
+/// `SIGALRM` al despachador cuando el hilo en CPU agota su quantum. Se
+/// detiene al soltarlo.
+///
+/// Solo avisa con el reloj real: con `ClockMode::Virtual` el tiempo avanza
+/// en los puntos de expropiación, y una señal a destiempo haría que dos
+/// corridas iguales dejaran de serlo.
+pub(crate) struct Timer {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Timer {
+    /// Arranca el vigía para el runtime `rt`, que despacha en este hilo del SO.
+    ///
+    /// Si la plataforma no permite expropiar por señal devuelve un vigía
+    /// vacío, y solo quedan los puntos de expropiación cooperativos.
+    pub(crate) fn start(rt: Arc<RuntimeInner>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = signal::install().then(|| {
+            let target = signal::Target::current();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || watch(&rt.scheduler, target, &stop))
+        });
+        Self { stop, handle }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+// ciclo del vigía: espera lo que le falta al quantum y avisa si se acabó
+fn watch(s: &SchedulerState, target: signal::Target, stop: &AtomicBool) {
+    while !stop.load(Ordering::SeqCst) {
+        let real_clock = util::clock_mode(s) == ClockMode::Real;
+        let wait_ms = match remaining(s).filter(|_| enabled(s) && real_clock) {
+            Some(0) => {
+                target.interrupt();
+                RETRY_MS
+            }
+            Some(left) => left,
+            None => IDLE_POLL_MS,
+        };
+        thread::park_timeout(Duration::from_millis(wait_ms));
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod signal {
+    use std::ffi::c_void;
+    use std::fs;
+    use std::mem;
+    use std::ptr;
+    use std::sync::OnceLock;
+
+    use crate::mypthreads::thread::preempt_current;
+    use super::{switch_depth, NoPreempt};
+
+    /// Rangos de código ejecutable del binario que contiene a la biblioteca.
+    /// Se expropia solo si la señal interrumpió código de ahí.
+    static PROGRAM_TEXT: OnceLock<Option<Vec<(usize, usize)>>> = OnceLock::new();
+
+    /// Hilo del SO al que se le avisa.
+    #[derive(Clone, Copy)]
+    pub(super) struct Target(libc::pthread_t);
+
+    impl Target {
+        pub(super) fn current() -> Self {
+            // SAFETY: `pthread_self` no tiene precondiciones.
+            Self(unsafe { libc::pthread_self() })
+        }
+
+        pub(super) fn interrupt(self) {
+            // SAFETY: el `Timer` se detiene antes de que `my_thread_run`
+            // retorne, así que el hilo destino sigue vivo.
+            unsafe {
+                libc::pthread_kill(self.0, libc::SIGALRM);
+            }
+        }
+    }
+
+    /// Instala (una sola vez) el manejador de `SIGALRM`; `false` si no se
+    /// puede expropiar por señal en este proceso.
+    pub(super) fn install() -> bool {
+        PROGRAM_TEXT
+            .get_or_init(|| {
+                let text = program_text()?;
+                // SAFETY: `on_sigalrm` tiene la firma de `sa_sigaction`.
+                unsafe {
+                    let mut action: libc::sigaction = mem::zeroed();
+                    action.sa_sigaction = on_sigalrm as *const () as usize;
+                    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    if libc::sigaction(libc::SIGALRM, &action, ptr::null_mut()) != 0 {
+                        return None;
+                    }
+                }
+                Some(text)
+            })
+            .is_some()
+    }
+
+    /// Rangos ejecutables de `/proc/self/maps` que son del mismo archivo que
+    /// esta función.
+    fn program_text() -> Option<Vec<(usize, usize)>> {
+        let maps = fs::read_to_string("/proc/self/maps").ok()?;
+        let here = program_text as *const () as usize;
+        let mappings: Vec<(usize, usize, bool, &str)> = maps
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let (start, end) = fields.next()?.split_once('-')?;
+                let exec = fields.next()?.contains('x');
+                let path = fields.nth(3).unwrap_or("");
+                Some((
+                    usize::from_str_radix(start, 16).ok()?,
+                    usize::from_str_radix(end, 16).ok()?,
+                    exec,
+                    path,
+                ))
+            })
+            .collect();
+        let (_, _, _, path) = *mappings.iter().find(|&&(start, end, _, _)| (start..end).contains(&here))?;
+        if path.is_empty() {
+            return None;
+        }
+        Some(
+            mappings
+                .iter()
+                .filter(|&&(_, _, exec, p)| exec && p == path)
+                .map(|&(start, end, _, _)| (start, end))
+                .collect(),
+        )
+    }
+
+    /// Dirección donde la señal interrumpió al hilo.
+    ///
+    /// # Safety
+    /// `ucontext` debe ser el tercer argumento de un manejador `SA_SIGINFO`.
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn interrupted_pc(ucontext: *mut c_void) -> usize {
+        let uc = &*(ucontext as *const libc::ucontext_t);
+        uc.uc_mcontext.gregs[libc::REG_RIP as usize] as usize
+    }
+
+    /// Dirección donde la señal interrumpió al hilo.
+    ///
+    /// # Safety
+    /// `ucontext` debe ser el tercer argumento de un manejador `SA_SIGINFO`.
+    #[cfg(target_arch = "aarch64")]
+    unsafe fn interrupted_pc(ucontext: *mut c_void) -> usize {
+        let uc = &*(ucontext as *const libc::ucontext_t);
+        uc.uc_mcontext.pc as usize
+    }
+
+    /// ¿Interrumpió la señal código del programa (y no de libc u otra biblioteca)?
+    fn in_program(pc: usize) -> bool {
+        match PROGRAM_TEXT.get() {
+            Some(Some(text)) => text.iter().any(|&(start, end)| (start..end).contains(&pc)),
+            _ => false,
+        }
+    }
+
+    extern "C" fn on_sigalrm(_signal: libc::c_int, _info: *mut libc::siginfo_t, ucontext: *mut c_void) {
+        // SAFETY: el kernel pasa el `ucontext_t` del hilo interrumpido.
+        let pc = unsafe { interrupted_pc(ucontext) };
+        if switch_depth() > 0 || std::thread::panicking() || !in_program(pc) {
+            return;
+        }
+
+        // sin expropiaciones anidadas hasta volver a este hilo
+        let _no_preempt = NoPreempt::new();
+        // SAFETY: `errno` es del hilo del SO; se le devuelve al hilo interrumpido.
+        let errno = unsafe { *libc::__errno_location() };
+        // si cambiamos de contexto no se sale del manejador hasta que este
+        // hilo vuelva a correr: desbloquear la señal para los demás
+        unsafe {
+            let mut set: libc::sigset_t = mem::zeroed();
+            libc::sigemptyset(&mut set);
+            libc::sigaddset(&mut set, libc::SIGALRM);
+            libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, ptr::null_mut());
+        }
+
+        preempt_current();
+
+        unsafe {
+            *libc::__errno_location() = errno;
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod signal {
+    /// Sin expropiación por señal: no hay a quién avisar.
+    #[derive(Clone, Copy)]
+    pub(super) struct Target;
+
+    impl Target {
+        pub(super) fn current() -> Self {
+            Self
+        }
+
+        pub(super) fn interrupt(self) {}
+    }
+
+    pub(super) fn install() -> bool {
+        false
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::mypthreads::{MyThreadId, ThreadControlBlock, ThreadTable};
+
+/// Cola de hilos READY de una clase, en orden de llegada.
+///
+/// Se mantiene en cada transición (crear, ceder, bloquearse, despertar,
+/// terminar, cambiar de clase), así que elegir no necesita recorrer la tabla
+/// de hilos. Meter y sacar cuestan O(log n).
+#[derive(Debug, Default)]
+pub(super) struct RunQueue {
+    /// Número de llegada → hilo.
+    order: BTreeMap<u64, MyThreadId>,
+    /// Hilo → (número de llegada, hora de llegada en ms).
+    position: HashMap<MyThreadId, (u64, u64)>,
+    next_seq: u64,
+}
+
+impl RunQueue {
+    /// Agrega `tid` al final, anotando que llegó a la hora `now_ms`; `false`
+    /// si ya estaba.
+    pub fn push(&mut self, tid: MyThreadId, now_ms: u64) -> bool {
+        if self.position.contains_key(&tid) {
+            return false;
+        }
+        self.order.insert(self.next_seq, tid);
+        self.position.insert(tid, (self.next_seq, now_ms));
+        self.next_seq += 1;
+        true
+    }
+
+    /// Saca a `tid`; `false` si no estaba.
+    pub fn remove(&mut self, tid: MyThreadId) -> bool {
+        match self.position.remove(&tid) {
+            Some((seq, _)) => {
+                self.order.remove(&seq);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// El que lleva más tiempo en la cola y desde qué hora espera.
+    pub fn oldest(&self) -> Option<(MyThreadId, u64)> {
+        let tid = self.first()?;
+        Some((tid, self.position[&tid].1))
+    }
+
+    pub fn contains(&self, tid: MyThreadId) -> bool {
+        self.position.contains_key(&tid)
+    }
+
+    /// El que lleva más tiempo en la cola.
+    pub fn first(&self) -> Option<MyThreadId> {
+        self.order.values().next().copied()
+    }
+
+    /// Recorre la cola en orden de llegada.
+    pub fn iter(&self) -> impl Iterator<Item = MyThreadId> + '_ {
+        self.order.values().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// Cola de hilos ordenada por una clave (deadline, pass, `vruntime`, ...);
+/// a igualdad de clave, por ID. Meter, sacar y ver el primero: O(log n).
+#[derive(Debug)]
+pub(super) struct KeyedQueue<K> {
+    set: BTreeSet<(K, MyThreadId)>,
+    key: HashMap<MyThreadId, K>,
+}
+
+impl<K> Default for KeyedQueue<K> {
+    fn default() -> Self {
+        Self { set: BTreeSet::new(), key: HashMap::new() }
+    }
+}
+
+impl<K: Ord + Copy> KeyedQueue<K> {
+    /// Mete a `tid` con clave `key` (si ya estaba, la reemplaza).
+    pub fn insert(&mut self, tid: MyThreadId, key: K) {
+        self.remove(tid);
+        self.set.insert((key, tid));
+        self.key.insert(tid, key);
+    }
+
+    pub fn remove(&mut self, tid: MyThreadId) {
+        if let Some(key) = self.key.remove(&tid) {
+            self.set.remove(&(key, tid));
+        }
+    }
+
+    /// El de menor clave, con su clave.
+    pub fn first(&self) -> Option<(K, MyThreadId)> {
+        self.set.first().copied()
+    }
+}
+
+/// Hilos READY de una clase, tal como se los pasa el planificador a
+/// `Scheduler::pick_next`: en orden de llegada, el que más espera primero.
+pub struct Ready<'a> {
+    queue: &'a RunQueue,
+    table: &'a ThreadTable,
+}
+
+impl<'a> Ready<'a> {
+    pub(super) fn new(queue: &'a RunQueue, table: &'a ThreadTable) -> Self {
+        Self { queue, table }
+    }
+
+    /// Recorre los candidatos en orden de llegada.
+    pub fn iter(&self) -> impl Iterator<Item = &'a ThreadControlBlock> + '_ {
+        self.queue.order.values().filter_map(|&tid| self.table.get(tid))
+    }
+
+    /// El que lleva más tiempo esperando.
+    pub fn first(&self) -> Option<&'a ThreadControlBlock> {
+        self.iter().next()
+    }
+
+    /// El TCB de `tid`, si es uno de los candidatos.
+    pub fn get(&self, tid: MyThreadId) -> Option<&'a ThreadControlBlock> {
+        if self.contains(tid) {
+            self.table.get(tid)
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, tid: MyThreadId) -> bool {
+        self.queue.contains(tid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.order.is_empty()
+    }
+}
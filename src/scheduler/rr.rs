@@ -1,15 +1,26 @@
-use super::util::RR_CURSOR;
+use crate::mypthreads::MyThreadId;
+use super::policy::Scheduler;
+use super::queue::Ready;
 
-/// Round Robin: rotación justa con cursor circular.
-pub(super) fn pick(rr_ready: Vec<usize>) -> Option<usize> {
-    if rr_ready.is_empty() {
-        return None;
+/// Round Robin: el que más tiempo lleva esperando en la cola de la clase.
+///
+/// Un hilo que cede el CPU vuelve al final de la cola, así que el orden de
+/// rotación no cambia cuando otros hilos se bloquean o terminan.
+#[derive(Debug, Default)]
+pub struct RrScheduler;
+
+impl RrScheduler {
+    pub fn new() -> Self {
+        Self
     }
-    let mut cursor = RR_CURSOR.lock().expect("RR_CURSOR poisoned");
-    if *cursor >= rr_ready.len() {
-        *cursor = 0;
+}
+
+impl Scheduler for RrScheduler {
+    fn name(&self) -> &str {
+        "round-robin"
+    }
+
+    fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId> {
+        ready.first().map(|t| t.id)
     }
-    let chosen = rr_ready[*cursor];
-    *cursor = (*cursor + 1) % rr_ready.len();
-    Some(chosen)
 }
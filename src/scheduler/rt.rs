@@ -1,9 +1,77 @@
-/// Tiempo Real: elegir READY con menor `deadline_ms`.
-pub(super) fn pick(rt_ready: &mut Vec<(usize, u64)>) -> Option<usize> {
-    if rt_ready.is_empty() {
-        return None;
-    }
-    // Ordenar por deadline ascendente y tomar el primero (igual a tu código).
-    rt_ready.sort_by_key(|&(_, d)| d);
-    Some(rt_ready[0].0)
+use crate::mypthreads::{DeadlineMissAction, MyThreadId, RtParams, ThreadControlBlock};
+use super::policy::Scheduler;
+use super::queue::{KeyedQueue, Ready};
+
+/// Tiempo Real (EDF): elegir READY con menor `deadline_ms`.
+///
+/// Los hilos RT sin deadline van después de todos los que tienen uno, en
+/// orden de ID.
+#[derive(Debug, Default)]
+pub struct RtScheduler {
+    /// Hilos READY por deadline absoluto.
+    queue: KeyedQueue<u64>,
+}
+
+impl RtScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Scheduler for RtScheduler {
+    fn name(&self) -> &str {
+        "real-time"
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        self.queue.insert(t.id, t.deadline_ms.unwrap_or(u64::MAX));
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        self.queue.remove(tid);
+    }
+
+    fn pick_next(&mut self, _ready: &Ready<'_>) -> Option<MyThreadId> {
+        self.queue.first().map(|(_, tid)| tid)
+    }
+}
+
+/// Política de la clase `RealTime` (`scheduler::set_rt_policy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RtPolicy {
+    /// Prioridades dinámicas: el deadline absoluto más cercano.
+    #[default]
+    Edf,
+    /// Prioridades fijas por período (menor período, más prioridad).
+    RateMonotonic,
+    /// Prioridades fijas por deadline relativo.
+    DeadlineMonotonic,
+}
+
+/// Un hilo `RealTime` que no cumplió su deadline (`scheduler::deadline_miss_events`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineMiss {
+    pub tid: MyThreadId,
+    /// Deadline incumplido, en el reloj del planificador.
+    pub deadline_ms: u64,
+    /// Cuánto tarde se detectó (o terminó el trabajo, si era periódico).
+    pub lateness_ms: u64,
+    /// Hora del reloj del planificador en que se detectó.
+    pub at_ms: u64,
+    /// Reacción que se aplicó.
+    pub action: DeadlineMissAction,
+}
+
+/// Densidad total de un conjunto de tareas: `Σ wcet / min(deadline, period)`.
+/// Con deadlines implícitos es la utilización.
+pub fn edf_density(tasks: &[RtParams]) -> f64 {
+    tasks.iter().map(RtParams::density).sum()
+}
+
+/// Test de admisión de EDF: el conjunto es planificable si su densidad no
+/// pasa de 1 (condición exacta con deadlines implícitos, suficiente si son
+/// menores que el período).
+pub fn edf_schedulable(tasks: &[RtParams]) -> bool {
+    // margen para el redondeo de sumas como 1/3 + 1/3 + 1/3
+    edf_density(tasks) <= 1.0 + 1e-9
 }
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::mypthreads::{MyThreadId, ThreadControlBlock};
+use super::policy::Scheduler;
+use super::queue::{KeyedQueue, Ready};
+
+/// Constante de stride: un hilo con `t` tickets avanza `STRIDE1 / t` por turno.
+const STRIDE1: u64 = 1 << 20;
+
+/// Stride: reparto proporcional a los `tickets`, como Lottery, pero sin azar.
+///
+/// Cada hilo tiene un *pass* que avanza `STRIDE1 / tickets` cada vez que se
+/// despacha; gana siempre el de menor pass (a igualdad, el de menor ID). Con
+/// la misma carga, dos corridas dan exactamente el mismo orden, y el error de
+/// reparto queda acotado en vez de crecer con la raíz del número de sorteos.
+#[derive(Debug, Default)]
+pub struct StrideScheduler {
+    /// Hilos READY por pass.
+    queue: KeyedQueue<u64>,
+    /// Pass de cada hilo que está compitiendo.
+    pass: HashMap<MyThreadId, u64>,
+    /// Pass que les faltaba gastar a los hilos bloqueados; al volver se les
+    /// suma al pass global para que no acumulen turnos mientras esperan.
+    remain: HashMap<MyThreadId, u64>,
+    /// Pass del último hilo elegido (el "tiempo virtual" de la clase).
+    global_pass: u64,
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Avance por turno de un hilo (0 tickets cuenta como 1).
+fn stride(t: &ThreadControlBlock) -> u64 {
+    STRIDE1 / t.tickets.max(1) as u64
+}
+
+impl Scheduler for StrideScheduler {
+    fn name(&self) -> &str {
+        "stride"
+    }
+
+    fn enqueue(&mut self, t: &ThreadControlBlock) {
+        let pass = *self.pass.entry(t.id).or_insert_with(|| {
+            // recién llegado (o despertado): arranca en el pass global
+            self.global_pass + self.remain.remove(&t.id).unwrap_or(0)
+        });
+        self.queue.insert(t.id, pass);
+    }
+
+    fn dequeue(&mut self, tid: MyThreadId) {
+        self.queue.remove(tid);
+    }
+
+    fn on_block(&mut self, tid: MyThreadId) {
+        if let Some(pass) = self.pass.remove(&tid) {
+            self.remain.insert(tid, pass.saturating_sub(self.global_pass));
+        }
+    }
+
+    fn on_exit(&mut self, tid: MyThreadId) {
+        self.pass.remove(&tid);
+        self.remain.remove(&tid);
+    }
+
+    fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId> {
+        let (pass, tid) = self.queue.first()?;
+        let next_pass = pass + ready.get(tid).map_or(STRIDE1, stride);
+
+        self.global_pass = pass;
+        self.pass.insert(tid, next_pass);
+        self.queue.insert(tid, next_pass);
+        Some(tid)
+    }
+}
@@ -1,36 +1,100 @@
-use once_cell::sync::Lazy;
-use std::sync::{Mutex, atomic::{AtomicBool, Ordering}};
-use std::time::Instant;
+use std::sync::atomic::Ordering;
 
-/// Cola que mantenías (aunque hoy no se usa para elegir).
-/// Se conserva porque tu `scheduler_add()` la usa.
-pub(super) static RUN_QUEUE: Lazy<Mutex<Vec<usize>>> =
-    Lazy::new(|| Mutex::new(Vec::new()));
+use super::SchedulerState;
 
-/// Índice circular para Round Robin (sobre los candidatos READY de tipo RR).
-pub(super) static RR_CURSOR: Lazy<Mutex<usize>> =
-    Lazy::new(|| Mutex::new(0));
+// El reloj y la explosión viven en el `SchedulerState` de cada
+// runtime (ver `mypthreads::Runtime`); aquí solo se manipulan.
 
-// Set del reloj y explosión ----
-static START: Lazy<Instant> = Lazy::new(Instant::now);
-static EXPLODED: AtomicBool = AtomicBool::new(false);
+/// Fuente de tiempo del planificador (`scheduler::now_ms`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockMode {
+    /// Tiempo real desde que se creó el runtime.
+    #[default]
+    Real,
+    /// Reloj simulado: solo avanza `tick_ms` en cada despacho y en cada punto
+    /// de expropiación, salta directo al próximo hilo dormido cuando nadie
+    /// está listo, y con `scheduler::advance_clock`. Una corrida completa se
+    /// repite exactamente igual y no espera tiempo real.
+    Virtual { tick_ms: u64 },
+}
+
+/// Reloj de un runtime.
+#[derive(Debug, Default)]
+pub(super) struct Clock {
+    pub mode: ClockMode,
+    /// Hora del reloj simulado (ms).
+    pub virtual_ms: u64,
+}
+
+// tiempo del reloj del runtime (ms)
+pub(super) fn now_ms(s: &SchedulerState) -> u64 {
+    let clock = s.clock.lock().unwrap();
+    match clock.mode {
+        ClockMode::Real => real_ms(s),
+        ClockMode::Virtual { .. } => clock.virtual_ms,
+    }
+}
+
+// tiempo real transcurrido desde que se creó el runtime (ms)
+fn real_ms(s: &SchedulerState) -> u64 {
+    s.start.elapsed().as_millis() as u64
+}
+
+// cambia la fuente de tiempo; el reloj simulado arranca en la hora actual
+pub(super) fn set_clock_mode(s: &SchedulerState, mode: ClockMode) {
+    let now = now_ms(s);
+    let mut clock = s.clock.lock().unwrap();
+    if matches!(mode, ClockMode::Virtual { .. }) && clock.mode == ClockMode::Real {
+        clock.virtual_ms = now;
+    }
+    clock.mode = mode;
+}
+
+pub(super) fn clock_mode(s: &SchedulerState) -> ClockMode {
+    s.clock.lock().unwrap().mode
+}
+
+// adelanta el reloj simulado `ms` (no hace nada con el reloj real)
+pub(super) fn advance(s: &SchedulerState, ms: u64) {
+    let mut clock = s.clock.lock().unwrap();
+    if let ClockMode::Virtual { .. } = clock.mode {
+        clock.virtual_ms = clock.virtual_ms.saturating_add(ms);
+    }
+}
 
-pub(super) fn push_run_queue(tid: usize) {
-    let mut rq = RUN_QUEUE.lock().unwrap();
-    rq.push(tid);
+// un tick de trabajo simulado
+pub(super) fn tick(s: &SchedulerState) {
+    let tick_ms = match clock_mode(s) {
+        ClockMode::Virtual { tick_ms } => tick_ms,
+        ClockMode::Real => return,
+    };
+    advance(s, tick_ms);
 }
 
-// tiempo transcurrido desde el inicio (ms)
-pub(super) fn now_ms() -> u64 {
-    START.elapsed().as_millis() as u64
+// espera (o salta, con el reloj simulado) hasta el instante `until_ms`
+pub(super) fn wait_until(s: &SchedulerState, until_ms: u64) {
+    {
+        let mut clock = s.clock.lock().unwrap();
+        if let ClockMode::Virtual { .. } = clock.mode {
+            clock.virtual_ms = clock.virtual_ms.max(until_ms);
+            return;
+        }
+    }
+    let remaining = until_ms.saturating_sub(real_ms(s));
+    std::thread::sleep(std::time::Duration::from_millis(remaining));
 }
 
 // marcar explosión
-pub(super) fn mark_explosion() {
-    EXPLODED.store(true, Ordering::SeqCst);
+pub(super) fn mark_explosion(s: &SchedulerState) {
+    s.exploded.store(true, Ordering::SeqCst);
+}
+
+// deshacer la explosión (`scheduler::reset_explosion`)
+pub(super) fn reset_explosion(s: &SchedulerState) {
+    s.exploded.store(false, Ordering::SeqCst);
 }
 
 // leer estado de explosión (lo exponemos via mod.rs)
-pub(super) fn exploded() -> bool {
-    EXPLODED.load(Ordering::SeqCst)
-}
\ No newline at end of file
+pub(super) fn exploded(s: &SchedulerState) -> bool {
+    s.exploded.load(Ordering::SeqCst)
+}
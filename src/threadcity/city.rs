@@ -1,6 +1,7 @@
 // city.rs - tiny city model with very simple movement logic
-use crate::mypthreads::MyMutex;
-use crate::mypthreads::{my_mutex_lock, my_mutex_unlock, MyThreadId};
+use crate::mypthreads::{MyCond, MyMutex, MySemaphore, MutexProtocol};
+use crate::mypthreads::{my_cond_broadcast, my_cond_wait, my_mutex_lock, my_mutex_unlock, my_sem_post, my_sem_wait, MyThreadId};
+use crate::mypthreads::{my_thread_cleanup_pop, my_thread_cleanup_push, my_thread_sleep};
 use std::sync::{Arc};
 use crate::threadcity::entities::{Vehicle, VehicleType, Bridge, BridgeType};
 
@@ -15,12 +16,22 @@ pub struct City {
 
 impl City {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::with_protocol(width, height, MutexProtocol::None)
+    }
+
+    /// Igual que `new`, pero los mutex de los puentes usan `protocol`
+    /// (p. ej. `MutexProtocol::Inherit` para que una ambulancia RT no quede
+    /// atrapada detrás de un auto RR que tiene el puente).
+    pub fn with_protocol(width: usize, height: usize, protocol: MutexProtocol) -> Self {
         let bridges = vec![
     Bridge {
         id: 1,
         name: "Puente Norte".into(),
         bridge_type: BridgeType::TrafficLight,
-        mutex: Arc::new(MyMutex::new()),
+        mutex: Arc::new(MyMutex::with_protocol(protocol)),
+        cond: Arc::new(MyCond::new()),
+        lanes: Arc::new(MySemaphore::new(BridgeType::TrafficLight.lanes())),
+        boat_turn: Arc::new(MyMutex::with_protocol(protocol)),
         is_blocked: Arc::new(std::sync::Mutex::new(false)),
         green_light: Arc::new(std::sync::Mutex::new(true)),
     },
@@ -28,7 +39,10 @@ impl City {
         id: 2,
         name: "Puente Central".into(),
         bridge_type: BridgeType::YieldSign,
-        mutex: Arc::new(MyMutex::new()),
+        mutex: Arc::new(MyMutex::with_protocol(protocol)),
+        cond: Arc::new(MyCond::new()),
+        lanes: Arc::new(MySemaphore::new(BridgeType::YieldSign.lanes())),
+        boat_turn: Arc::new(MyMutex::with_protocol(protocol)),
         is_blocked: Arc::new(std::sync::Mutex::new(false)),
         green_light: Arc::new(std::sync::Mutex::new(true)),
     },
@@ -36,7 +50,10 @@ impl City {
         id: 3,
         name: "Puente Sur".into(),
         bridge_type: BridgeType::TwoLanes,
-        mutex: Arc::new(MyMutex::new()),
+        mutex: Arc::new(MyMutex::with_protocol(protocol)),
+        cond: Arc::new(MyCond::new()),
+        lanes: Arc::new(MySemaphore::new(BridgeType::TwoLanes.lanes())),
+        boat_turn: Arc::new(MyMutex::with_protocol(protocol)),
         is_blocked: Arc::new(std::sync::Mutex::new(false)),
         green_light: Arc::new(std::sync::Mutex::new(true)),
     },
@@ -63,6 +80,20 @@ impl City {
     }
 // step() devuelve un bool
     pub fn step(&mut self, tid: MyThreadId) -> bool {
+    let (all_arrived, crossings) = self.advance();
+
+    // 🚗 Ya fuera del for (cuando no hay préstamos mutables)
+    // ejecutamos los cruces
+    for (vtype, bridge_id) in crossings {
+    self.cross_bridge(vtype, bridge_id, tid);  // Use bridge_id, not hardcoded 1
+}
+
+    all_arrived
+}
+
+    /// Mueve cada vehículo una casilla sin cruzar puentes (no bloquea).
+    /// Devuelve si ya todos llegaron y los cruces pendientes (tipo, puente).
+    pub fn advance(&mut self) -> (bool, Vec<(VehicleType, usize)>) {
     let mut all_arrived = true;
     let mut crossings = Vec::new(); // 👈 aquí guardaremos qué vehículos deben cruzar
 
@@ -86,13 +117,7 @@ impl City {
         }
     }
 
-    // 🚗 Ya fuera del for (cuando no hay préstamos mutables)
-    // ejecutamos los cruces
-    for (vtype, bridge_id) in crossings {
-    self.cross_bridge(vtype, bridge_id, tid);  // Use bridge_id, not hardcoded 1
-}
-
-    all_arrived
+    (all_arrived, crossings)
 }
 
 
@@ -120,8 +145,18 @@ impl City {
 }
 
 pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyThreadId) {
-    let bridge = &self.bridges[bridge_id - 1];
-    
+    Self::cross(&self.bridges[bridge_id - 1], vehicle_type, tid);
+}
+
+    /// Copia del puente `bridge_id` para cruzarlo con `City::cross` después de
+    /// soltar la ciudad: cruzar duerme y espera a otros vehículos.
+    pub fn bridge(&self, bridge_id: usize) -> Bridge {
+        self.bridges[bridge_id - 1].clone()
+    }
+
+/// Cruza `bridge` con un vehículo de tipo `vehicle_type` (la lógica de
+/// `cross_bridge`, sin necesitar la ciudad).
+pub fn cross(bridge: &Bridge, vehicle_type: VehicleType, tid: MyThreadId) {
     println!("{} quiere cruzar el {}", 
         match vehicle_type {
             VehicleType::Ambulance => "🚑 Ambulancia",
@@ -138,20 +173,20 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
             // BRIDGE 1: Traffic light + 1 lane
             // Ambulances get immediate priority
             if vehicle_type == VehicleType::Ambulance {
-                my_mutex_lock(&bridge.mutex, tid);
+                my_mutex_lock(&bridge.mutex, tid).unwrap();
                 println!("🚑 Ambulancia cruzando {} (PRIORIDAD)", bridge.name);
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                my_thread_sleep(500);
                 my_mutex_unlock(&bridge.mutex, tid).unwrap();
                 println!("🚑 Ambulancia salió del {}", bridge.name);
                 return;
             }
 
-            // Wait for green light
-            loop {
-                let green = *bridge.green_light.lock().unwrap();
-                if green {
-                    break;
-                }
+            // Wait for green light (sleeping on the bridge condition)
+            my_mutex_lock(&bridge.mutex, tid).unwrap();
+            // if the vehicle is canceled while waiting, give the bridge back
+            let mutex = Arc::clone(&bridge.mutex);
+            my_thread_cleanup_push(move || { let _ = my_mutex_unlock(&mutex, tid); });
+            while !*bridge.green_light.lock().unwrap() {
                 println!("🔴 {} esperando luz verde", 
                     match vehicle_type {
                         VehicleType::Car => "Auto",
@@ -159,10 +194,10 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                         VehicleType::SupplyTruck => "Camión",
                         _ => "Vehículo",
                     });
-                std::thread::sleep(std::time::Duration::from_millis(100));
+                my_cond_wait(&bridge.cond, &bridge.mutex, tid).unwrap();
             }
+            my_thread_cleanup_pop(false);
 
-            my_mutex_lock(&bridge.mutex,tid);
             println!("🟢 {} cruzando {} (luz verde)", 
                 match vehicle_type {
                     VehicleType::Car => "Auto",
@@ -172,7 +207,7 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                 },
                 bridge.name
             );
-            std::thread::sleep(std::time::Duration::from_millis(800));
+            my_thread_sleep(800);
             let _ = my_mutex_unlock(&bridge.mutex, tid);
             println!("✅ Salió del {}", bridge.name);
         }
@@ -181,9 +216,9 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
             // BRIDGE 2: Yield sign + 1 lane
             // Ambulances get priority
             if vehicle_type == VehicleType::Ambulance {
-                my_mutex_lock(&bridge.mutex, tid);
+                my_mutex_lock(&bridge.mutex, tid).unwrap();
                 println!("🚑 Ambulancia cruzando {} (PRIORIDAD)", bridge.name);
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                my_thread_sleep(500);
                  my_mutex_unlock(&bridge.mutex, tid).unwrap();
                 println!("🚑 Ambulancia salió del {}", bridge.name);
                 return;
@@ -199,9 +234,9 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                 },
                 bridge.name
             );
-            std::thread::sleep(std::time::Duration::from_millis(200));
+            my_thread_sleep(200);
 
-            my_mutex_lock(&bridge.mutex,tid);
+            my_mutex_lock(&bridge.mutex, tid).unwrap();
             println!("➡️ {} cruzando {}", 
                 match vehicle_type {
                     VehicleType::Car => "Auto",
@@ -211,7 +246,7 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                 },
                 bridge.name
             );
-            std::thread::sleep(std::time::Duration::from_millis(800));
+            my_thread_sleep(800);
             let _ = my_mutex_unlock(&bridge.mutex, tid);
             println!("✅ Salió del {}", bridge.name);
         }
@@ -222,26 +257,40 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
             // If it's a boat, BLOCK the bridge
             if vehicle_type == VehicleType::Boat {
                 println!("⛵ Barco acercándose - BLOQUEANDO {}", bridge.name);
-                
-                // Block the bridge
+
+                // One boat at a time: two boats holding one lane each would
+                // wait for each other forever
+                my_mutex_lock(&bridge.boat_turn, tid).unwrap();
+                my_mutex_lock(&bridge.mutex, tid).unwrap();
                 *bridge.is_blocked.lock().unwrap() = true;
-                
-                my_mutex_lock(&bridge.mutex, tid);
+                my_mutex_unlock(&bridge.mutex, tid).unwrap();
+
+                // Block the bridge: take every lane so the cars already on it drain first
+                let lanes = bridge.bridge_type.lanes();
+                for _ in 0..lanes {
+                    my_sem_wait(&bridge.lanes, tid);
+                }
                 println!("🚢 Barco pasando bajo {} (puente BLOQUEADO)", bridge.name);
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-                
-                // Unblock
+                my_thread_sleep(2000);
+                for _ in 0..lanes {
+                    my_sem_post(&bridge.lanes);
+                }
+
+                // Unblock and wake everyone waiting for the bridge
+                my_mutex_lock(&bridge.mutex, tid).unwrap();
+                *bridge.is_blocked.lock().unwrap() = false;
+                my_cond_broadcast(&bridge.cond);
                 my_mutex_unlock(&bridge.mutex, tid).unwrap();
+                my_mutex_unlock(&bridge.boat_turn, tid).unwrap();
                 println!("✅ Barco pasó - {} libre nuevamente", bridge.name);
                 return;
             }
 
-            // Wait if blocked by boat
-            loop {
-                let blocked = *bridge.is_blocked.lock().unwrap();
-                if !blocked {
-                    break;
-                }
+            // Wait if blocked by boat (sleeping on the bridge condition)
+            my_mutex_lock(&bridge.mutex, tid).unwrap();
+            let mutex = Arc::clone(&bridge.mutex);
+            my_thread_cleanup_push(move || { let _ = my_mutex_unlock(&mutex, tid); });
+            while *bridge.is_blocked.lock().unwrap() {
                 println!("🛑 {} esperando - {} bloqueado por barco", 
                     match vehicle_type {
                         VehicleType::Car => "Auto",
@@ -251,13 +300,19 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                     },
                     bridge.name
                 );
-                std::thread::sleep(std::time::Duration::from_millis(100));
+                my_cond_wait(&bridge.cond, &bridge.mutex, tid).unwrap();
             }
+            my_thread_cleanup_pop(false);
+            my_mutex_unlock(&bridge.mutex, tid).unwrap();
+
+            // Take one of the lanes (waits if both are in use)
+            my_sem_wait(&bridge.lanes, tid);
 
             // Ambulances still get priority
             if vehicle_type == VehicleType::Ambulance {
                 println!("🚑 Ambulancia cruzando {} (PRIORIDAD, 2 carriles)", bridge.name);
-                std::thread::sleep(std::time::Duration::from_millis(400));
+                my_thread_sleep(400);
+                my_sem_post(&bridge.lanes);
                 println!("🚑 Ambulancia salió del {}", bridge.name);
                 return;
             }
@@ -271,12 +326,23 @@ pub fn cross_bridge(&self, vehicle_type: VehicleType, bridge_id: usize, tid: MyT
                 },
                 bridge.name
             );
-            std::thread::sleep(std::time::Duration::from_millis(600)); // Faster
+            my_thread_sleep(600); // Faster
+            my_sem_post(&bridge.lanes);
             println!("✅ Salió del {}", bridge.name);
         }
     }
 }
 
+    /// Cambia el semáforo del puente `bridge_id` y despierta a quien esté
+    /// esperando la luz verde.
+    pub fn set_green_light(&self, bridge_id: usize, green: bool, tid: MyThreadId) {
+        let bridge = &self.bridges[bridge_id - 1];
+        my_mutex_lock(&bridge.mutex, tid).unwrap();
+        *bridge.green_light.lock().unwrap() = green;
+        my_cond_broadcast(&bridge.cond);
+        let _ = my_mutex_unlock(&bridge.mutex, tid);
+    }
+
     /// Devuelve una copia del estado actual para que la GUI pueda dibujar.
     pub fn snapshot(&self) -> (usize, usize, Vec<Vehicle>) {
         (self.width, self.height, self.vehicles.clone())
@@ -1,4 +1,4 @@
-use crate::mypthreads::MyMutex;
+use crate::mypthreads::{MyCond, MyMutex, MySemaphore};
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +16,16 @@ pub enum BridgeType {
     TwoLanes,      // Puente 3
 }
 
+impl BridgeType {
+    /// Cuántos vehículos pueden estar sobre el puente a la vez.
+    pub fn lanes(&self) -> usize {
+        match self {
+            BridgeType::TwoLanes => 2,
+            BridgeType::TrafficLight | BridgeType::YieldSign => 1,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Vehicle {
     pub id: usize,
@@ -26,12 +36,19 @@ pub struct Vehicle {
 
 use std::sync::{Arc};
 
-#[derive(Debug)]
+/// Las copias comparten los mutex, la condición y los semáforos del puente.
+#[derive(Debug, Clone)]
 pub struct Bridge {
     pub id: usize,
     pub name: String,
     pub bridge_type: BridgeType,           
     pub mutex: Arc<MyMutex>,
+    /// Avisa cambios de `is_blocked` / `green_light` (protegidos por `mutex`).
+    pub cond: Arc<MyCond>,
+    /// Un permiso por carril: limita cuántos vehículos cruzan a la vez.
+    pub lanes: Arc<MySemaphore>,
+    /// Turno de los barcos: uno a la vez toma todos los carriles y pasa.
+    pub boat_turn: Arc<MyMutex>,
     pub is_blocked: Arc<std::sync::Mutex<bool>>,
     pub green_light: Arc<std::sync::Mutex<bool>>,   
 }
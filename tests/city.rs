@@ -0,0 +1,35 @@
+//! Pruebas de la ciudad: cruces de los puentes.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use proyecto1::mypthreads::{my_thread_create, my_thread_id, Runtime, SchedulerType};
+use proyecto1::scheduler::{self, ClockMode};
+use proyecto1::threadcity::{City, VehicleType};
+
+#[test]
+fn two_boats_and_two_cars_all_cross_the_two_lane_bridge() {
+    let rt = Runtime::new();
+    let city = Arc::new(City::new(10, 5));
+    let crossed = Arc::new(AtomicUsize::new(0));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+
+        // los autos ocupan los dos carriles primero: cada barco se queda
+        // esperando permisos mientras los autos los van soltando
+        for vtype in [VehicleType::Car, VehicleType::Car, VehicleType::Boat, VehicleType::Boat] {
+            let city = Arc::clone(&city);
+            let crossed = Arc::clone(&crossed);
+            my_thread_create(
+                move || {
+                    city.cross_bridge(vtype, 3, my_thread_id());
+                    crossed.fetch_add(1, Ordering::SeqCst);
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+        }
+    });
+    rt.run();
+    assert_eq!(crossed.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,581 @@
+//! Pruebas de las políticas de planificación y del modo expropiativo.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use proyecto1::mypthreads::{
+    my_thread_chsched, my_thread_create, my_thread_id, my_thread_join, my_thread_preempt_point,
+    my_thread_run, my_thread_set_deadline_ms, my_thread_set_miss_action, my_thread_set_nice,
+    my_thread_set_rt_params, my_thread_set_tickets, my_thread_spawn, my_thread_wait_next_period,
+    my_thread_yield_, with_threads, DeadlineMissAction, MyMutex, MyThreadError, MyThreadId,
+    RtParams, Runtime, SchedulerType, ThreadState,
+};
+use proyecto1::scheduler::{
+    self, ClassArbitration, ClockMode, DeadlineMiss, Ready, Scheduler, StarvationIncident,
+};
+
+/// Corre hilos de Lottery con distintos tickets en un runtime con `seed` y
+/// devuelve el orden en que recibieron el CPU.
+fn lottery_schedule(seed: u64) -> Vec<MyThreadId> {
+    let rt = Runtime::with_seed(seed);
+    let order = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_quantum_ms(SchedulerType::Lottery, 4);
+        for (i, tickets) in [1, 5, 10, 20].into_iter().enumerate() {
+            let order = Arc::clone(&order);
+            let tid = my_thread_create(
+                move || {
+                    for _ in 0..20 {
+                        order.lock().unwrap().push(my_thread_id());
+                        // unos agotan el quantum y otros ceden antes (compensación)
+                        if i % 2 == 0 {
+                            my_thread_yield_();
+                        } else {
+                            for _ in 0..8 {
+                                my_thread_preempt_point();
+                            }
+                        }
+                    }
+                },
+                SchedulerType::Lottery,
+            )
+            .unwrap();
+            my_thread_set_tickets(tid, tickets).unwrap();
+        }
+    });
+    rt.run();
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn timer_preempts_a_thread_that_never_calls_the_library() {
+    let rt = Runtime::new();
+    let released = Arc::new(AtomicBool::new(false));
+    rt.enter(|| {
+        scheduler::set_preemption(true);
+        scheduler::set_quantum_ms(SchedulerType::RoundRobin, 5);
+
+        // sin expropiación por señal, este ciclo no suelta nunca el CPU
+        let flag = Arc::clone(&released);
+        my_thread_create(
+            move || {
+                while !flag.load(Ordering::SeqCst) {
+                    std::hint::spin_loop();
+                }
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+
+        let flag = Arc::clone(&released);
+        my_thread_create(move || flag.store(true, Ordering::SeqCst), SchedulerType::RoundRobin).unwrap();
+    });
+    rt.run();
+    assert!(released.load(Ordering::SeqCst));
+}
+
+#[test]
+fn same_lottery_seed_gives_the_same_schedule() {
+    let first = lottery_schedule(7);
+    assert_eq!(first.len(), 80);
+    assert_eq!(first, lottery_schedule(7));
+    assert_ne!(first, lottery_schedule(8));
+}
+
+#[test]
+fn lottery_seed_is_reported_for_replay() {
+    let rt = Runtime::with_seed(42);
+    rt.enter(|| {
+        assert_eq!(scheduler::policy_seed(SchedulerType::Lottery), Some(42));
+        // con un generador propio la semilla ya no se conoce
+        scheduler::set_lottery_rng(rand::rngs::mock::StepRng::new(0, 1));
+        assert_eq!(scheduler::policy_seed(SchedulerType::Lottery), None);
+    });
+}
+
+/// Dos hilos de `class` ceden 300 veces cada uno; `apply` le pone a cada uno
+/// su parámetro de `params` (tickets, nice). Devuelve cuántos turnos recibió
+/// el segundo mientras el primero seguía compitiendo.
+fn contended_turns<P: Copy>(
+    class: SchedulerType,
+    params: [P; 2],
+    apply: fn(MyThreadId, P),
+) -> usize {
+    let rt = Runtime::new();
+    let turns = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        for (i, param) in params.into_iter().enumerate() {
+            let turns = Arc::clone(&turns);
+            let tid = my_thread_create(
+                move || {
+                    for _ in 0..300 {
+                        turns.lock().unwrap().push(i);
+                        my_thread_yield_();
+                    }
+                },
+                class,
+            )
+            .unwrap();
+            apply(tid, param);
+        }
+    });
+    rt.run();
+
+    let turns = turns.lock().unwrap();
+    let first_done = turns.iter().rposition(|&i| i == 0).unwrap();
+    turns[..first_done].iter().filter(|&&i| i == 1).count()
+}
+
+#[test]
+fn stride_splits_turns_in_proportion_to_tickets() {
+    // mientras los dos compiten, el de 300 tickets recibe 3 turnos por cada 1
+    let poor = contended_turns(SchedulerType::Stride, [300, 100], |tid, tickets| {
+        my_thread_set_tickets(tid, tickets).unwrap()
+    });
+    assert!((98..=102).contains(&poor), "poor recibió {poor} turnos");
+}
+
+/// Dos hilos de `class` gastan CPU sin ceder; a mitad de camino el primero
+/// crea otro. Devuelve quién recibe el CPU cuando el primero lo suelta.
+fn next_after_newcomer(class: SchedulerType) -> &'static str {
+    let rt = Runtime::new();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        scheduler::set_preemption(true);
+        scheduler::set_quantum_ms(class, 2);
+        for name in ["first", "second"] {
+            let log = Arc::clone(&log);
+            my_thread_create(
+                move || {
+                    for step in 0..30 {
+                        if name == "first" && step == 10 {
+                            let newcomer = Arc::clone(&log);
+                            my_thread_create(move || newcomer.lock().unwrap().push("newcomer"), class)
+                                .unwrap();
+                            log.lock().unwrap().push("spawned");
+                        }
+                        log.lock().unwrap().push(name);
+                        my_thread_preempt_point();
+                    }
+                },
+                class,
+            )
+            .unwrap();
+        }
+    });
+    rt.run();
+
+    let log = log.lock().unwrap();
+    let spawned = log.iter().position(|&n| n == "spawned").unwrap();
+    log[spawned..].iter().copied().find(|&n| n != "first" && n != "spawned").unwrap()
+}
+
+#[test]
+fn mlfq_demotes_threads_that_use_their_quantum() {
+    // Round Robin respeta el orden de llegada: el hilo nuevo va a la cola
+    assert_eq!(next_after_newcomer(SchedulerType::RoundRobin), "second");
+    // en MLFQ los dos hilos que gastaron su asignación ya bajaron de nivel, y
+    // el nuevo (en el nivel más alto) pasa antes
+    assert_eq!(next_after_newcomer(SchedulerType::Mlfq), "newcomer");
+}
+
+#[test]
+fn cfs_weights_cpu_time_by_nice() {
+    // nice 5 pesa 335 contra 1024 de nice 0: un tercio del CPU
+    let niced = contended_turns(SchedulerType::Cfs, [0, 5], |tid, nice| {
+        my_thread_set_nice(tid, nice).unwrap()
+    });
+    assert!((85..=115).contains(&niced), "nice 5 recibió {niced} turnos");
+}
+
+#[test]
+fn cfs_runs_the_lowest_vruntime_and_charges_the_current_nice() {
+    let rt = Runtime::new();
+    let tids = Arc::new(Mutex::new(Vec::new()));
+    // (quién corrió, si acababa de subir su nice, su vruntime, el del otro)
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        for i in 0..2 {
+            let (peers, log) = (Arc::clone(&tids), Arc::clone(&log));
+            let tid = my_thread_create(
+                move || {
+                    let me = my_thread_id();
+                    let other = peers.lock().unwrap()[1 - i];
+                    for step in 0..100 {
+                        if i == 0 && step == 20 {
+                            // desde aquí cada turno le cuesta ~9 veces más
+                            my_thread_set_nice(me, 10).unwrap();
+                        }
+                        let (mine, theirs) = with_threads(|table| {
+                            let theirs = table
+                                .get(other)
+                                .filter(|t| t.state != ThreadState::Finished)
+                                .map(|t| t.vruntime);
+                            (table[me].vruntime, theirs)
+                        });
+                        if let Some(theirs) = theirs {
+                            log.lock().unwrap().push((i, step >= 20, mine, theirs));
+                        }
+                        my_thread_yield_();
+                    }
+                },
+                SchedulerType::Cfs,
+            )
+            .unwrap();
+            tids.lock().unwrap().push(tid);
+        }
+    });
+    rt.run();
+
+    let log = log.lock().unwrap();
+    // siempre corre el de menor vruntime (a igualdad, el de menor ID)
+    assert!(log.iter().all(|&(_, _, mine, theirs)| mine <= theirs));
+    // con nice 10 (peso 110 contra 1024) recibe ~1 turno por cada 9 del otro
+    let reniced = log.iter().filter(|&&(i, after, ..)| i == 0 && after).count();
+    let other = log.iter().filter(|&&(i, ..)| i == 1).count() - 20;
+    assert!(reniced * 7 <= other && other <= reniced * 12, "{reniced} contra {other} turnos");
+}
+
+/// Un hilo de Lottery acapara el CPU durante 200 turnos; devuelve en qué
+/// turno alcanzó a correr el hilo de Round Robin (si corrió antes del final)
+/// y los incidentes de inanición.
+fn starved_turn(arbitration: ClassArbitration) -> (Option<usize>, Vec<StarvationIncident>) {
+    let rt = Runtime::new();
+    let turn = Arc::new(Mutex::new(0));
+    let rescued_at = Arc::new(Mutex::new(None));
+    let incidents = rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        scheduler::set_class_arbitration(arbitration);
+        scheduler::set_starvation_threshold_ms(20);
+
+        let counter = Arc::clone(&turn);
+        my_thread_create(
+            move || {
+                for _ in 0..200 {
+                    *counter.lock().unwrap() += 1;
+                    my_thread_yield_();
+                }
+            },
+            SchedulerType::Lottery,
+        )
+        .unwrap();
+
+        let (counter, rescued) = (Arc::clone(&turn), Arc::clone(&rescued_at));
+        my_thread_create(
+            move || *rescued.lock().unwrap() = Some(*counter.lock().unwrap()),
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+        scheduler::starvation_incidents()
+    });
+    let rescued_at = rescued_at.lock().unwrap().filter(|&turn| turn < 200);
+    (rescued_at, incidents)
+}
+
+#[test]
+fn aging_rescues_a_starved_lower_class() {
+    // con prioridad estricta, Round Robin espera a que Lottery termine
+    let (turn, incidents) = starved_turn(ClassArbitration::Strict);
+    assert_eq!(turn, None);
+    assert!(incidents.iter().all(|i| !i.rescued));
+
+    // con envejecimiento, corre en cuanto lleva 50 ms esperando
+    let (turn, incidents) = starved_turn(ClassArbitration::Aging { max_wait_ms: 50 });
+    assert!(turn.is_some_and(|turn| turn <= 60), "corrió en el turno {turn:?}");
+    assert!(incidents.iter().any(|i| i.class == SchedulerType::RoundRobin && i.rescued));
+}
+
+#[test]
+fn blocked_lottery_thread_lends_its_tickets_to_the_owner() {
+    let rt = Runtime::with_seed(11);
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        let mutex = Arc::new(MyMutex::new());
+        let (m, owner_log) = (Arc::clone(&mutex), Arc::clone(&log));
+        my_thread_create(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+
+                let (waiter_mutex, waiter_log) = (Arc::clone(&m), Arc::clone(&owner_log));
+                let waiter = my_thread_create(
+                    move || {
+                        let me = my_thread_id();
+                        waiter_mutex.lock(me).unwrap();
+                        waiter_log.lock().unwrap().push("waiter");
+                        waiter_mutex.unlock(me).unwrap();
+                    },
+                    SchedulerType::Lottery,
+                )
+                .unwrap();
+                my_thread_set_tickets(waiter, 1000).unwrap();
+
+                let rival_log = Arc::clone(&owner_log);
+                let rival = my_thread_create(
+                    move || {
+                        for _ in 0..200 {
+                            rival_log.lock().unwrap().push("rival");
+                            my_thread_yield_();
+                        }
+                    },
+                    SchedulerType::Lottery,
+                )
+                .unwrap();
+                my_thread_set_tickets(rival, 10).unwrap();
+
+                // con 1 ticket propio, solo gana los sorteos con lo que le presta el que espera
+                for _ in 0..20 {
+                    owner_log.lock().unwrap().push("owner");
+                    my_thread_yield_();
+                }
+                m.unlock(me).unwrap();
+            },
+            SchedulerType::Lottery,
+        )
+        .unwrap();
+    });
+    rt.run();
+
+    let log = log.lock().unwrap();
+    let released = log.iter().position(|&n| n == "waiter").unwrap();
+    let rival_turns = log[..released].iter().filter(|&&n| n == "rival").count();
+    assert!(rival_turns < 20, "el rival ganó {rival_turns} sorteos");
+}
+
+/// Un hilo RT con `action` se pasa 5 ms de su deadline. Devuelve cómo terminó,
+/// los incumplimientos anotados y si explotó la planta.
+fn late_thread(
+    action: DeadlineMissAction,
+) -> (Result<u32, MyThreadError>, Vec<DeadlineMiss>, bool) {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        let late = my_thread_spawn(
+            || {
+                scheduler::advance_clock(10);
+                // el despacho de acá detecta el incumplimiento
+                my_thread_yield_();
+                7
+            },
+            SchedulerType::RealTime,
+        )
+        .unwrap();
+        my_thread_set_deadline_ms(late.id(), Some(scheduler::now_ms() + 5)).unwrap();
+        my_thread_set_miss_action(late.id(), action).unwrap();
+        my_thread_run();
+        (late.join(), scheduler::deadline_miss_events(), scheduler::plant_exploded())
+    })
+}
+
+#[test]
+fn ignored_and_logged_misses_are_only_recorded() {
+    for action in [DeadlineMissAction::Ignore, DeadlineMissAction::Log] {
+        let (result, misses, exploded) = late_thread(action);
+        assert_eq!(result, Ok(7));
+        assert_eq!(misses.len(), 1);
+        assert_eq!(misses[0].action, action);
+        assert!(misses[0].lateness_ms > 0);
+        assert!(!exploded);
+    }
+}
+
+#[test]
+fn aborted_miss_cancels_the_thread() {
+    let (result, misses, exploded) = late_thread(DeadlineMissAction::Abort);
+    assert!(matches!(result, Err(MyThreadError::Canceled(_))));
+    assert_eq!(misses.len(), 1);
+    assert!(!exploded);
+}
+
+#[test]
+fn escalated_miss_explodes_the_plant() {
+    let (result, misses, exploded) = late_thread(DeadlineMissAction::Escalate);
+    assert_eq!(result, Ok(7));
+    assert_eq!(misses.len(), 1);
+    assert!(exploded);
+}
+
+#[test]
+fn explosion_and_miss_log_can_be_reset() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        let late = my_thread_create(
+            || {
+                scheduler::advance_clock(10);
+                my_thread_yield_();
+            },
+            SchedulerType::RealTime,
+        )
+        .unwrap();
+        my_thread_set_deadline_ms(late, Some(scheduler::now_ms() + 5)).unwrap();
+        my_thread_run();
+        assert!(scheduler::plant_exploded());
+        assert_eq!(scheduler::deadline_misses(), 1);
+
+        scheduler::reset_explosion();
+        scheduler::clear_deadline_miss_events();
+        assert!(!scheduler::plant_exploded());
+        assert_eq!(scheduler::deadline_misses(), 0);
+        assert!(scheduler::deadline_miss_events().is_empty());
+    });
+}
+
+/// Siempre elige al hilo READY con mayor ID: lo contrario del orden de llegada.
+struct HighestId;
+
+impl Scheduler for HighestId {
+    fn name(&self) -> &str {
+        "highest-id"
+    }
+
+    fn pick_next(&mut self, ready: &Ready<'_>) -> Option<MyThreadId> {
+        ready.iter().map(|t| t.id).max()
+    }
+}
+
+/// Un hilo Round Robin y tres de la clase propia `Custom(0)` (con `HighestId`),
+/// creados en ese orden; cada uno anota su nombre y termina. Devuelve el orden
+/// en que corrieron.
+fn custom_class_order(precedence: Option<&[SchedulerType]>) -> Vec<&'static str> {
+    let rt = Runtime::new();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::register_policy(SchedulerType::Custom(0), Box::new(HighestId));
+        if let Some(precedence) = precedence {
+            scheduler::set_class_precedence(precedence);
+        }
+        for (name, class) in [
+            ("rr", SchedulerType::RoundRobin),
+            ("c1", SchedulerType::Custom(0)),
+            ("c2", SchedulerType::Custom(0)),
+            ("c3", SchedulerType::Custom(0)),
+        ] {
+            let order = Arc::clone(&order);
+            my_thread_create(move || order.lock().unwrap().push(name), class).unwrap();
+        }
+    });
+    rt.run();
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn registered_policy_picks_for_its_class_in_precedence_order() {
+    // la clase nueva se consulta al final, y dentro de ella decide `HighestId`
+    assert_eq!(custom_class_order(None), ["rr", "c3", "c2", "c1"]);
+    // puesta antes que Round Robin, sus hilos corren primero
+    let custom_first = [SchedulerType::Custom(0), SchedulerType::RoundRobin];
+    assert_eq!(custom_class_order(Some(&custom_first)), ["c3", "c2", "c1", "rr"]);
+}
+
+#[test]
+fn edf_admission_rejects_an_overloaded_task_set() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        let truck = my_thread_create(|| {}, SchedulerType::RealTime).unwrap();
+        my_thread_set_rt_params(truck, Some(RtParams::implicit(20, 5))).unwrap();
+
+        // 5/20 + 16/20 > 1: rechazada, y el hilo queda como estaba
+        let ambulance = my_thread_create(|| {}, SchedulerType::RealTime).unwrap();
+        assert_eq!(
+            my_thread_set_rt_params(ambulance, Some(RtParams::implicit(20, 16))),
+            Err(MyThreadError::NotSchedulable(ambulance))
+        );
+        assert_eq!(with_threads(|table| table[ambulance].rt_params), None);
+        // 5/20 + 15/20 = 1 todavía cabe
+        my_thread_set_rt_params(ambulance, Some(RtParams::implicit(20, 15))).unwrap();
+
+        // pasar a RealTime un hilo con parámetros también pasa por la admisión
+        let boat = my_thread_create(|| {}, SchedulerType::RoundRobin).unwrap();
+        my_thread_set_rt_params(boat, Some(RtParams::implicit(40, 1))).unwrap();
+        assert_eq!(
+            my_thread_chsched(boat, SchedulerType::RealTime),
+            Err(MyThreadError::NotSchedulable(boat))
+        );
+        assert_eq!(with_threads(|table| table[boat].scheduler_type), SchedulerType::RoundRobin);
+        my_thread_run();
+    });
+}
+
+#[test]
+fn periodic_thread_is_released_once_per_period() {
+    let rt = Runtime::new();
+    // (liberación, deadline) de cada trabajo, relativos al arranque
+    let jobs = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        let start = scheduler::now_ms();
+        let log = Arc::clone(&jobs);
+        let sensor = my_thread_create(
+            move || {
+                let me = my_thread_id();
+                for _ in 0..4 {
+                    let deadline = with_threads(|table| table[me].deadline_ms).unwrap();
+                    log.lock().unwrap().push((scheduler::now_ms() - start, deadline - start));
+                    my_thread_wait_next_period().unwrap();
+                }
+            },
+            SchedulerType::RealTime,
+        )
+        .unwrap();
+        my_thread_set_rt_params(sensor, Some(RtParams::new(10, 8, 1))).unwrap();
+        my_thread_run();
+        assert_eq!(scheduler::deadline_misses(), 0);
+    });
+    // cada trabajo se libera un período después del anterior (el reloj
+    // simulado avanza 1 ms al despacharlo) con su deadline a 8 ms
+    let jobs = jobs.lock().unwrap();
+    let deadlines: Vec<_> = jobs.iter().map(|&(_, deadline)| deadline).collect();
+    assert_eq!(deadlines, [8, 18, 28, 38]);
+    for (k, &(started, _)) in jobs.iter().enumerate() {
+        let release = 10 * k as u64;
+        assert!((release..release + 2).contains(&started), "trabajo {k} empezó en {started}");
+    }
+}
+
+#[test]
+fn round_robin_rotation_survives_threads_that_block_and_finish() {
+    let rt = Runtime::new();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        let mut tids = Vec::new();
+        for (name, turns) in [("a", 4), ("b", 2), ("c", 4)] {
+            let log = Arc::clone(&log);
+            let tid = my_thread_create(
+                move || {
+                    for turn in 1..=turns {
+                        log.lock().unwrap().push(name);
+                        if turn < turns {
+                            my_thread_yield_();
+                        }
+                    }
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+            tids.push(tid);
+        }
+        // `d` espera a que `b` termine y entra al final de la rotación
+        let (b, log) = (tids[1], Arc::clone(&log));
+        my_thread_create(
+            move || {
+                my_thread_join(b).unwrap();
+                log.lock().unwrap().push("d");
+                my_thread_yield_();
+                log.lock().unwrap().push("d");
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+    });
+
+    let log = log.lock().unwrap();
+    assert_eq!(*log, ["a", "b", "c", "a", "b", "c", "a", "d", "c", "a", "d", "c"]);
+}
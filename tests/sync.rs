@@ -0,0 +1,352 @@
+//! Pruebas de las primitivas de sincronización de `mypthreads`.
+
+use std::sync::{Arc, Mutex};
+
+use proyecto1::mypthreads::{
+    my_thread_cancel, my_thread_cleanup_push, my_thread_create, my_thread_id, my_thread_join,
+    my_thread_run, my_thread_set_deadline_ms, my_thread_set_rt_params, my_thread_spawn,
+    my_thread_yield_, set_deadlock_detection, with_threads, MutexProtocol, MyCond, MyMutex,
+    MyThreadError, MyThreadId, RtParams, Runtime, SchedulerType, ThreadState,
+};
+use proyecto1::scheduler::{self, ClockMode, RtPolicy};
+
+#[test]
+fn waiters_block_and_take_the_mutex_in_arrival_order() {
+    let rt = Runtime::new();
+    let mutex = Arc::new(MyMutex::new());
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let waiters = Arc::new(Mutex::new(Vec::<MyThreadId>::new()));
+    let all_blocked = Arc::new(Mutex::new(false));
+    rt.enter(|| {
+        let (m, w, b) = (Arc::clone(&mutex), Arc::clone(&waiters), Arc::clone(&all_blocked));
+        my_thread_create(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+                // los demás piden el mutex ocupado y se quedan esperando
+                my_thread_yield_();
+                *b.lock().unwrap() = with_threads(|table| {
+                    w.lock().unwrap().iter().all(|&tid| table[tid].state == ThreadState::Blocked)
+                });
+                m.unlock(me).unwrap();
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+
+        for _ in 0..3 {
+            let (m, order) = (Arc::clone(&mutex), Arc::clone(&order));
+            let tid = my_thread_create(
+                move || {
+                    let me = my_thread_id();
+                    m.lock(me).unwrap();
+                    order.lock().unwrap().push(me);
+                    m.unlock(me).unwrap();
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+            waiters.lock().unwrap().push(tid);
+        }
+        my_thread_run();
+    });
+    // en espera no compiten por el CPU, y el mutex pasa en orden de llegada
+    assert!(*all_blocked.lock().unwrap());
+    assert_eq!(*order.lock().unwrap(), *waiters.lock().unwrap());
+}
+
+/// Un hilo RR toma el mutex; luego llegan uno RealTime que lo pide y uno Cfs
+/// que solo quiere CPU. Devuelve el orden en que terminan su trabajo.
+fn inversion_scenario(protocol: MutexProtocol) -> Vec<&'static str> {
+    let rt = Runtime::new();
+    let mutex = Arc::new(MyMutex::with_protocol(protocol));
+    let order = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        let (m, log) = (Arc::clone(&mutex), Arc::clone(&order));
+        my_thread_create(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+
+                let (m2, log2) = (Arc::clone(&m), Arc::clone(&log));
+                let high = my_thread_create(
+                    move || {
+                        let me = my_thread_id();
+                        m2.lock(me).unwrap();
+                        log2.lock().unwrap().push("high");
+                        m2.unlock(me).unwrap();
+                    },
+                    SchedulerType::RealTime,
+                )
+                .unwrap();
+                my_thread_set_deadline_ms(high, Some(scheduler::now_ms() + 10_000)).unwrap();
+
+                let log3 = Arc::clone(&log);
+                my_thread_create(move || log3.lock().unwrap().push("medium"), SchedulerType::Cfs)
+                    .unwrap();
+
+                // el RT corre, pide el mutex y se bloquea
+                my_thread_yield_();
+                log.lock().unwrap().push("low");
+                m.unlock(me).unwrap();
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+    });
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn priority_inheritance_runs_the_owner_before_medium_work() {
+    // sin protocolo, el hilo Cfs se adelanta al dueño y con él al RT
+    assert_eq!(inversion_scenario(MutexProtocol::None), ["medium", "low", "high"]);
+    // con herencia, el dueño corre como RT hasta soltar el mutex
+    assert_eq!(inversion_scenario(MutexProtocol::Inherit), ["low", "high", "medium"]);
+}
+
+/// Lo mismo con Rate Monotonic y tres hilos RT: el dueño tiene el período más
+/// largo, el que pide el mutex el más corto y el de trabajo suelto uno medio.
+fn rate_monotonic_inversion(protocol: MutexProtocol) -> Vec<&'static str> {
+    let rt = Runtime::new();
+    let mutex = Arc::new(MyMutex::with_protocol(protocol));
+    let order = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        scheduler::set_rt_policy(RtPolicy::RateMonotonic);
+        let (m, log) = (Arc::clone(&mutex), Arc::clone(&order));
+        let low = my_thread_create(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+
+                let (m2, log2) = (Arc::clone(&m), Arc::clone(&log));
+                let high = my_thread_create(
+                    move || {
+                        let me = my_thread_id();
+                        m2.lock(me).unwrap();
+                        log2.lock().unwrap().push("high");
+                        m2.unlock(me).unwrap();
+                    },
+                    SchedulerType::RealTime,
+                )
+                .unwrap();
+                my_thread_set_rt_params(high, Some(RtParams::implicit(10, 1))).unwrap();
+
+                let log3 = Arc::clone(&log);
+                let medium = my_thread_create(
+                    move || log3.lock().unwrap().push("medium"),
+                    SchedulerType::RealTime,
+                )
+                .unwrap();
+                my_thread_set_rt_params(medium, Some(RtParams::implicit(100, 1))).unwrap();
+
+                // el de período corto corre, pide el mutex y se bloquea
+                my_thread_yield_();
+                log.lock().unwrap().push("low");
+                m.unlock(me).unwrap();
+            },
+            SchedulerType::RealTime,
+        )
+        .unwrap();
+        my_thread_set_rt_params(low, Some(RtParams::implicit(1_000, 1))).unwrap();
+        my_thread_run();
+    });
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn priority_inheritance_lends_the_fixed_priority_under_rate_monotonic() {
+    // sin protocolo, el de período medio se adelanta al dueño
+    assert_eq!(rate_monotonic_inversion(MutexProtocol::None), ["medium", "low", "high"]);
+    // con herencia, el dueño corre con el período del que espera
+    assert_eq!(rate_monotonic_inversion(MutexProtocol::Inherit), ["low", "high", "medium"]);
+}
+
+#[test]
+fn lock_that_closes_a_cycle_returns_deadlock() {
+    let rt = Runtime::new();
+    let (first, second) = (Arc::new(MyMutex::new()), Arc::new(MyMutex::new()));
+    rt.enter(|| {
+        set_deadlock_detection(true);
+        let (a, b) = (Arc::clone(&first), Arc::clone(&second));
+        let left = my_thread_spawn(
+            move || {
+                let me = my_thread_id();
+                a.lock(me).unwrap();
+                // el otro toma `b` y se queda esperando `a`
+                my_thread_yield_();
+                let result = b.lock(me);
+                a.unlock(me).unwrap();
+                result
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+
+        let (a, b) = (Arc::clone(&first), Arc::clone(&second));
+        let right = my_thread_spawn(
+            move || {
+                let me = my_thread_id();
+                b.lock(me).unwrap();
+                let result = a.lock(me);
+                a.unlock(me).unwrap();
+                b.unlock(me).unwrap();
+                result
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+
+        let left_id = left.id();
+        assert_eq!(left.join().unwrap(), Err(MyThreadError::Deadlock(left_id)));
+        assert_eq!(right.join().unwrap(), Ok(()));
+    });
+}
+
+#[test]
+fn join_that_closes_a_cycle_returns_deadlock() {
+    let rt = Runtime::new();
+    let ids = Arc::new(Mutex::new(Vec::new()));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        set_deadlock_detection(true);
+        // cada uno hace `join` sobre el otro: el primero espera, el segundo
+        // cerraría el ciclo
+        for other in [1, 0] {
+            let (all, results) = (Arc::clone(&ids), Arc::clone(&results));
+            let tid = my_thread_create(
+                move || {
+                    let target = all.lock().unwrap()[other];
+                    let result = my_thread_join(target);
+                    results.lock().unwrap().push((my_thread_id(), result));
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+            ids.lock().unwrap().push(tid);
+        }
+        my_thread_run();
+    });
+    let ids = ids.lock().unwrap().clone();
+    assert_eq!(
+        *results.lock().unwrap(),
+        [(ids[1], Err(MyThreadError::Deadlock(ids[1]))), (ids[0], Ok(()))]
+    );
+}
+
+#[test]
+fn a_cond_waiter_canceled_while_the_mutex_is_held_takes_it_back() {
+    let rt = Runtime::new();
+    let mutex = Arc::new(MyMutex::new());
+    let cond = Arc::new(MyCond::new());
+    rt.enter(|| {
+        let (m, c) = (Arc::clone(&mutex), Arc::clone(&cond));
+        let waiter = my_thread_spawn(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+                let held = Arc::clone(&m);
+                my_thread_cleanup_push(move || held.unlock(me).unwrap());
+                // nadie hace `signal`: solo sale por la cancelación
+                loop {
+                    c.wait(&m, me).unwrap();
+                }
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+
+        let (m, target) = (Arc::clone(&mutex), waiter.id());
+        my_thread_create(
+            move || {
+                let me = my_thread_id();
+                m.lock(me).unwrap();
+                my_thread_cancel(target).unwrap();
+                // el cancelado despierta y tiene que esperar a que soltemos el mutex
+                my_thread_yield_();
+                m.unlock(me).unwrap();
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+
+        let target = waiter.id();
+        assert_eq!(waiter.join(), Err::<(), _>(MyThreadError::Canceled(target)));
+    });
+    assert_eq!(mutex.owner(), None);
+}
+
+/// Tres hilos esperan en una `MyCond` hasta que haya un permiso para ellos; el
+/// último hilo reparte los permisos con `signal` (de a uno) o `broadcast` (los
+/// tres juntos). Devuelve quiénes corrieron después de cada aviso.
+fn cond_wakeups(broadcast: bool) -> Vec<Vec<&'static str>> {
+    let rt = Runtime::new();
+    let mutex = Arc::new(MyMutex::new());
+    let cond = Arc::new(MyCond::new());
+    let permits = Arc::new(Mutex::new(0));
+    let woken = Arc::new(Mutex::new(Vec::new()));
+    let rounds = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        for name in ["w1", "w2", "w3"] {
+            let (m, c) = (Arc::clone(&mutex), Arc::clone(&cond));
+            let (permits, woken) = (Arc::clone(&permits), Arc::clone(&woken));
+            my_thread_create(
+                move || {
+                    let me = my_thread_id();
+                    m.lock(me).unwrap();
+                    while *permits.lock().unwrap() == 0 {
+                        c.wait(&m, me).unwrap();
+                    }
+                    *permits.lock().unwrap() -= 1;
+                    woken.lock().unwrap().push(name);
+                    m.unlock(me).unwrap();
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+        }
+
+        let (m, c) = (Arc::clone(&mutex), Arc::clone(&cond));
+        let (permits, woken, log) = (Arc::clone(&permits), Arc::clone(&woken), Arc::clone(&rounds));
+        my_thread_create(
+            move || {
+                let me = my_thread_id();
+                let notices = if broadcast { 1 } else { 3 };
+                for _ in 0..notices {
+                    m.lock(me).unwrap();
+                    if broadcast {
+                        *permits.lock().unwrap() += 3;
+                        c.broadcast();
+                    } else {
+                        *permits.lock().unwrap() += 1;
+                        c.signal();
+                    }
+                    m.unlock(me).unwrap();
+                    // los despertados retoman el mutex y terminan
+                    for _ in 0..3 {
+                        my_thread_yield_();
+                    }
+                    log.lock().unwrap().push(std::mem::take(&mut *woken.lock().unwrap()));
+                }
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+    });
+    let rounds = rounds.lock().unwrap().clone();
+    rounds
+}
+
+#[test]
+fn signal_wakes_the_oldest_waiter_and_broadcast_wakes_all_in_order() {
+    assert_eq!(cond_wakeups(false), [vec!["w1"], vec!["w2"], vec!["w3"]]);
+    assert_eq!(cond_wakeups(true), [vec!["w1", "w2", "w3"]]);
+}
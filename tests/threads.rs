@@ -0,0 +1,226 @@
+//! Pruebas del ciclo de vida de los hilos: creación, `join`, salida, sueño y reciclaje.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use proyecto1::mypthreads::{
+    my_sem_wait, my_thread_cancel, my_thread_cleanup_push, my_thread_create, my_thread_exit,
+    my_thread_id, my_thread_join, my_thread_join_value, my_thread_max_threads, my_thread_run,
+    my_thread_sleep_until, my_thread_spawn, my_thread_yield_, with_threads, MySemaphore,
+    MyThreadError, Runtime, SchedulerType, ThreadState,
+};
+use proyecto1::scheduler::{self, ClockMode};
+
+#[test]
+fn joining_the_same_thread_twice_reports_already_joined() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        let tid = my_thread_create(|| {}, SchedulerType::RoundRobin).unwrap();
+        my_thread_run();
+
+        assert_eq!(my_thread_join(tid), Ok(()));
+        assert_eq!(
+            my_thread_join(tid),
+            Err(MyThreadError::AlreadyJoined { target: tid, waiter: None })
+        );
+    });
+}
+
+#[test]
+fn a_thread_that_joins_twice_is_named_as_the_joiner() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        let target = my_thread_create(|| {}, SchedulerType::RoundRobin).unwrap();
+        let joiner = my_thread_spawn(
+            move || (my_thread_join(target), my_thread_join(target), my_thread_id()),
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+
+        let (first, second, joiner_id) = joiner.join().unwrap();
+        assert_eq!(first, Ok(()));
+        assert_eq!(
+            second,
+            Err(MyThreadError::AlreadyJoined { target, waiter: Some(joiner_id) })
+        );
+    });
+}
+
+#[test]
+fn finished_slots_are_reused_past_the_thread_limit() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        let max = my_thread_max_threads();
+        let mut previous = Vec::new();
+        // tres tandas llenas: sin reciclar slots, la segunda ya no cabría
+        for _ in 0..3 {
+            let batch: Vec<_> = (0..max)
+                .map(|_| my_thread_create(|| {}, SchedulerType::RoundRobin).unwrap())
+                .collect();
+            assert_eq!(
+                my_thread_create(|| {}, SchedulerType::RoundRobin),
+                Err(MyThreadError::MaxThreadsReached { max })
+            );
+            my_thread_run();
+            for &tid in &batch {
+                my_thread_join(tid).unwrap();
+                // un ID viejo no apunta al hilo nuevo de su slot
+                assert!(!previous.contains(&tid));
+            }
+            previous = batch;
+        }
+        assert!(with_threads(|table| table.is_empty()));
+    });
+}
+
+#[test]
+fn a_canceled_waiter_is_joined_as_canceled() {
+    let rt = Runtime::new();
+    let cleaned_up = Arc::new(AtomicBool::new(false));
+    let finished_wait = Arc::new(AtomicBool::new(false));
+    let was_blocked = Arc::new(AtomicBool::new(false));
+    rt.enter(|| {
+        // nadie hace `post`: sin cancelación esperaría para siempre
+        let sem = Arc::new(MySemaphore::new(0));
+        let (cleaned, finished) = (Arc::clone(&cleaned_up), Arc::clone(&finished_wait));
+        let waiter = my_thread_spawn(
+            move || {
+                my_thread_cleanup_push(move || cleaned.store(true, Ordering::SeqCst));
+                my_sem_wait(&sem, my_thread_id());
+                finished.store(true, Ordering::SeqCst);
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+
+        let target = waiter.id();
+        let blocked = Arc::clone(&was_blocked);
+        my_thread_create(
+            move || {
+                let state = with_threads(|table| table[target].state);
+                blocked.store(state == ThreadState::Blocked, Ordering::SeqCst);
+                my_thread_cancel(target).unwrap();
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+
+        assert_eq!(waiter.join(), Err(MyThreadError::Canceled(target)));
+    });
+    assert!(was_blocked.load(Ordering::SeqCst));
+    assert!(cleaned_up.load(Ordering::SeqCst));
+    assert!(!finished_wait.load(Ordering::SeqCst));
+}
+
+#[test]
+fn spawned_threads_hand_their_values_to_join() {
+    let rt = Runtime::new();
+    rt.enter(|| {
+        let workers: Vec<_> = (1..=3)
+            .map(|i| my_thread_spawn(move || i * 10, SchedulerType::RoundRobin).unwrap())
+            .collect();
+        // un hilo recoge a los demás y devuelve la suma
+        let total = my_thread_spawn(
+            move || workers.into_iter().map(|w| w.join().unwrap()).sum::<i32>(),
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        let name = my_thread_spawn(|| String::from("listo"), SchedulerType::RoundRobin).unwrap();
+        my_thread_run();
+
+        assert_eq!(total.join(), Ok(60));
+        assert_eq!(name.join(), Ok(String::from("listo")));
+    });
+}
+
+/// Marca su bandera al destruirse.
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn exit_ends_the_thread_with_its_value_and_runs_destructors() {
+    let rt = Runtime::new();
+    let dropped = Arc::new(AtomicBool::new(false));
+    let after_exit = Arc::new(AtomicBool::new(false));
+    rt.enter(|| {
+        let (flag, after) = (Arc::clone(&dropped), Arc::clone(&after_exit));
+        let worker = my_thread_spawn(
+            move || {
+                let _guard = SetOnDrop(flag);
+                for step in 0..10u32 {
+                    if step == 3 {
+                        my_thread_exit(step * 100);
+                    }
+                    my_thread_yield_();
+                }
+                after.store(true, Ordering::SeqCst);
+                0u32
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        // con un valor de otro tipo, `join` no tiene qué devolver
+        let mismatched =
+            my_thread_spawn(|| -> u32 { my_thread_exit("no es u32") }, SchedulerType::RoundRobin)
+                .unwrap();
+        let mismatched_id = mismatched.id();
+        // un hilo de `my_thread_create` entrega el valor con `my_thread_join_value`
+        let plain = my_thread_create(|| my_thread_exit(7u8), SchedulerType::RoundRobin).unwrap();
+        my_thread_run();
+
+        assert_eq!(worker.join(), Ok(300));
+        assert_eq!(mismatched.join(), Err(MyThreadError::MissingExitValue(mismatched_id)));
+        let value = my_thread_join_value(plain).unwrap().unwrap();
+        assert_eq!(value.downcast_ref::<u8>(), Some(&7));
+    });
+    assert!(dropped.load(Ordering::SeqCst));
+    assert!(!after_exit.load(Ordering::SeqCst));
+}
+
+#[test]
+fn sleep_until_wakes_each_thread_at_its_time_while_others_run() {
+    let rt = Runtime::new();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    rt.enter(|| {
+        scheduler::set_clock_mode(ClockMode::Virtual { tick_ms: 1 });
+        let start = scheduler::now_ms();
+        for (name, wake_at) in [("late", start + 30), ("early", start + 10), ("past", start)] {
+            let log = Arc::clone(&log);
+            my_thread_create(
+                move || {
+                    my_thread_sleep_until(wake_at);
+                    log.lock().unwrap().push((name, scheduler::now_ms() - start));
+                },
+                SchedulerType::RoundRobin,
+            )
+            .unwrap();
+        }
+        let log = Arc::clone(&log);
+        my_thread_create(
+            move || {
+                for _ in 0..3 {
+                    log.lock().unwrap().push(("worker", scheduler::now_ms() - start));
+                    my_thread_yield_();
+                }
+            },
+            SchedulerType::RoundRobin,
+        )
+        .unwrap();
+        my_thread_run();
+    });
+
+    let log = log.lock().unwrap();
+    let names: Vec<_> = log.iter().map(|&(name, _)| name).collect();
+    // una hora ya pasada no duerme; mientras los otros duermen, el trabajador corre
+    assert_eq!(names, ["past", "worker", "worker", "worker", "early", "late"]);
+    let woke_at = |who| log.iter().find(|&&(name, _)| name == who).unwrap().1;
+    assert!((10..15).contains(&woke_at("early")), "despertó en {}", woke_at("early"));
+    assert!((30..35).contains(&woke_at("late")), "despertó en {}", woke_at("late"));
+}